@@ -0,0 +1,179 @@
+//! Token amounts
+
+use primitive_types::U256;
+
+use crate::error::Error;
+
+/// Amount of tokens as carried by swaps and VAAs. Foreign chains use 256 bit amounts while SPL
+/// token amounts are a `u64`, so every amount is checked before it is passed to the token program.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Amount(pub U256);
+
+impl Amount {
+    /// The amount as an SPL token amount, fails if it does not fit into a `u64`
+    pub fn to_token_amount(self) -> Result<u64, Error> {
+        if self.0 > U256::from(u64::MAX) {
+            return Err(Error::AmountOverflow);
+        }
+        Ok(self.0.as_u64())
+    }
+}
+
+impl From<U256> for Amount {
+    fn from(amount: U256) -> Self {
+        Amount(amount)
+    }
+}
+
+impl From<u64> for Amount {
+    fn from(amount: u64) -> Self {
+        Amount(U256::from(amount))
+    }
+}
+
+/// Decimals of the Croge token on a foreign chain and of the SPL mint. Amounts are scaled by the
+/// difference when they cross the bridge, equal decimals leave amounts unchanged. Until the
+/// mapping is set no amount can be scaled, so a chain cannot be used before its decimals are known.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecimalMapping {
+    /// decimals of the token on the foreign chain
+    pub foreign_decimals: u8,
+    /// decimals of the SPL mint
+    pub local_decimals: u8,
+    /// the decimals were set by the governor
+    pub is_set: bool,
+}
+
+impl DecimalMapping {
+    /// Largest number of decimals on either side, keeps the scale factor far within a `U256`
+    pub const MAX_DECIMALS: u8 = 38;
+
+    /// Checks that both sides are within `MAX_DECIMALS`
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.foreign_decimals > Self::MAX_DECIMALS || self.local_decimals > Self::MAX_DECIMALS {
+            return Err(Error::InvalidDecimals);
+        }
+        Ok(())
+    }
+
+    /// Scale factor between both sides, fails if the mapping is not set
+    fn factor(&self) -> Result<U256, Error> {
+        if !self.is_set {
+            return Err(Error::InvalidDecimals);
+        }
+        let diff = self.foreign_decimals.abs_diff(self.local_decimals);
+        Ok(U256::exp10(diff as usize))
+    }
+
+    /// Scales an amount of the SPL mint to the foreign chain. Returns the scaled amount and the
+    /// remainder that was truncated, in units of the mint.
+    pub fn to_foreign(&self, amount: Amount) -> Result<(Amount, Amount), Error> {
+        let factor = self.factor()?;
+        if self.foreign_decimals >= self.local_decimals {
+            let scaled = amount.0.checked_mul(factor).ok_or(Error::AmountOverflow)?;
+            Ok((Amount(scaled), Amount::default()))
+        } else {
+            Ok((Amount(amount.0 / factor), Amount(amount.0 % factor)))
+        }
+    }
+
+    /// Scales an amount of the foreign chain to the SPL mint. Returns the scaled amount and the
+    /// dust that was truncated, in foreign units.
+    pub fn to_local(&self, amount: Amount) -> Result<(Amount, Amount), Error> {
+        let factor = self.factor()?;
+        if self.foreign_decimals >= self.local_decimals {
+            Ok((Amount(amount.0 / factor), Amount(amount.0 % factor)))
+        } else {
+            let scaled = amount.0.checked_mul(factor).ok_or(Error::AmountOverflow)?;
+            Ok((Amount(scaled), Amount::default()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping(foreign_decimals: u8, local_decimals: u8) -> DecimalMapping {
+        DecimalMapping {
+            foreign_decimals,
+            local_decimals,
+            is_set: true,
+        }
+    }
+
+    #[test]
+    fn token_amount_fits_u64() {
+        assert_eq!(Amount::from(u64::MAX).to_token_amount(), Ok(u64::MAX));
+        assert_eq!(
+            Amount(U256::from(u64::MAX) + 1).to_token_amount(),
+            Err(Error::AmountOverflow)
+        );
+    }
+
+    #[test]
+    fn unset_mapping_scales_nothing() {
+        let m = DecimalMapping::default();
+        assert_eq!(m.to_foreign(Amount::from(1)), Err(Error::InvalidDecimals));
+        assert_eq!(m.to_local(Amount::from(1)), Err(Error::InvalidDecimals));
+    }
+
+    #[test]
+    fn equal_decimals_are_unchanged() {
+        let m = mapping(9, 9);
+        let amount = Amount(U256::max_value());
+        assert_eq!(m.to_foreign(amount), Ok((amount, Amount::default())));
+        assert_eq!(m.to_local(amount), Ok((amount, Amount::default())));
+    }
+
+    #[test]
+    fn scales_to_more_foreign_decimals() {
+        let m = mapping(18, 9);
+        assert_eq!(
+            m.to_foreign(Amount::from(1_500_000_000)),
+            Ok((
+                Amount(U256::from(1_500_000_000_000_000_000u64)),
+                Amount::default()
+            ))
+        );
+        assert_eq!(
+            m.to_local(Amount(U256::from(1_500_000_000_123_456_789u64))),
+            Ok((Amount::from(1_500_000_000), Amount::from(123_456_789)))
+        );
+        assert_eq!(m.to_foreign(Amount(U256::max_value())), Err(Error::AmountOverflow));
+    }
+
+    #[test]
+    fn scales_to_fewer_foreign_decimals() {
+        let m = mapping(6, 9);
+        assert_eq!(
+            m.to_foreign(Amount::from(2_000_000_000)),
+            Ok((Amount::from(2_000_000), Amount::default()))
+        );
+        assert_eq!(
+            m.to_foreign(Amount::from(2_000_000_999)),
+            Ok((Amount::from(2_000_000), Amount::from(999)))
+        );
+        assert_eq!(
+            m.to_local(Amount::from(2_000_000)),
+            Ok((Amount::from(2_000_000_000), Amount::default()))
+        );
+        assert_eq!(m.to_local(Amount(U256::max_value())), Err(Error::AmountOverflow));
+    }
+
+    #[test]
+    fn swap_round_trip() {
+        let m = mapping(18, 9);
+        let local = Amount::from(123_456_789);
+        let (foreign, _) = m.to_foreign(local).unwrap();
+        assert_eq!(m.to_local(foreign), Ok((local, Amount::default())));
+    }
+
+    #[test]
+    fn validate_limits_decimals() {
+        assert_eq!(mapping(38, 0).validate(), Ok(()));
+        assert_eq!(mapping(39, 9).validate(), Err(Error::InvalidDecimals));
+        assert_eq!(mapping(18, 39).validate(), Err(Error::InvalidDecimals));
+    }
+}
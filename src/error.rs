@@ -0,0 +1,244 @@
+//! Error types
+// `FromPrimitive` of num-derive 0.2 implements the trait inside a function
+#![allow(non_local_definitions)]
+
+use num_derive::FromPrimitive;
+use solana_program::{decode_error::DecodeError, program_error::ProgramError};
+use thiserror::Error;
+
+/// Errors that may be returned by the Bridge program.
+#[derive(Clone, Debug, Eq, Error, FromPrimitive, PartialEq)]
+pub enum Error {
+    /// The deserialization of the Token state returned something besides State::Token.
+    #[error("ExpectedToken")]
+    ExpectedToken,
+    /// The deserialization of the Token state returned something besides State::Account.
+    #[error("ExpectedAccount")]
+    ExpectedAccount,
+    /// The deserialization of the Bridge returned something besides State::Bridge.
+    #[error("ExpectedBridge")]
+    ExpectedBridge,
+    /// The deserialization of the GuardianSet returned something besides State::GuardianSet.
+    #[error("ExpectedGuardianSet")]
+    ExpectedGuardianSet,
+    /// The deserialization of the WrappedAssetMeta returned something besides State::WrappedAssetMeta.
+    #[error("ExpectedWrappedAssetMeta")]
+    ExpectedWrappedAssetMeta,
+    /// State is uninitialized.
+    #[error("State is unititialized")]
+    UninitializedState,
+    /// The program address provided doesn't match the value generated by the program.
+    #[error("InvalidProgramAddress")]
+    InvalidProgramAddress,
+    /// The submitted VAA is invalid
+    #[error("InvalidVAAFormat")]
+    InvalidVAAFormat,
+    /// The submitted VAA is invalid
+    #[error("InvalidVAAAction")]
+    InvalidVAAAction,
+    /// The submitted VAA has an invalid signature
+    #[error("InvalidVAASignature")]
+    InvalidVAASignature,
+    /// The account is already initialized
+    #[error("AlreadyExists")]
+    AlreadyExists,
+    /// An account was not derived correctly
+    #[error("InvalidDerivedAccount")]
+    InvalidDerivedAccount,
+    /// A given token account does not belong to the given mint
+    #[error("TokenMintMismatch")]
+    TokenMintMismatch,
+    /// A given mint account does not belong to the program
+    #[error("WrongMintOwner")]
+    WrongMintOwner,
+    /// A given token account does not belong to the program
+    #[error("WrongTokenAccountOwner")]
+    WrongTokenAccountOwner,
+    /// A parse operation failed
+    #[error("ParseFailed")]
+    ParseFailed,
+    /// The guardian set that signed this VAA has expired
+    #[error("GuardianSetExpired")]
+    GuardianSetExpired,
+    /// The given VAA has already been claimed
+    #[error("VAAClaimed")]
+    VAAClaimed,
+    /// The given VAA was not signed by the bridge owner
+    #[error("WrongBridgeOwner")]
+    WrongBridgeOwner,
+    /// The given VAA was not signed by the latest guardian set
+    #[error("OldGuardianSet")]
+    OldGuardianSet,
+    /// The given index is not the latest one
+    #[error("GuardianIndexNotIncreasing")]
+    GuardianIndexNotIncreasing,
+    /// The given transfer out proposal account is invalid
+    #[error("ExpectedTransferOutProposal")]
+    ExpectedTransferOutProposal,
+    /// The VAA does not match the proposal
+    #[error("VAAProposalMismatch")]
+    VAAProposalMismatch,
+    /// Invalid transfer with src=dst
+    #[error("SameChainTransfer")]
+    SameChainTransfer,
+    /// VAA is longer than the maximum size
+    #[error("VAATooLong")]
+    VAATooLong,
+    /// Cannot wrap a solana native asset
+    #[error("CannotWrapNative")]
+    CannotWrapNative,
+    /// VAA for this transfer has already been submitted
+    #[error("VAAAlreadySubmitted")]
+    VAAAlreadySubmitted,
+    /// Mismatching guardian set
+    #[error("GuardianSetMismatch")]
+    GuardianSetMismatch,
+    /// Insufficient fees
+    #[error("InsufficientFees")]
+    InsufficientFees,
+    /// Invalid owner
+    #[error("InvalidOwner")]
+    InvalidOwner,
+    /// Invalid Sysvar
+    #[error("InvalidSysvar")]
+    InvalidSysvar,
+    /// Invalid Chain
+    #[error("InvalidChain")]
+    InvalidChain,
+    /// The signer is not the governor of the bridge
+    #[error("InvalidGovernor")]
+    InvalidGovernor,
+    /// The inbound swap has already been processed
+    #[error("SwapAlreadyProcessed")]
+    SwapAlreadyProcessed,
+    /// Bridging is paused
+    #[error("BridgingPaused")]
+    BridgingPaused,
+    /// Swaps with the chain are not enabled
+    #[error("ChainDisabled")]
+    ChainDisabled,
+    /// The signer is not the system authority of the bridge
+    #[error("InvalidSystem")]
+    InvalidSystem,
+    /// The fee parameters are out of range
+    #[error("InvalidFee")]
+    InvalidFee,
+    /// The signer is not the upgrade authority of the program
+    #[error("InvalidUpgradeAuthority")]
+    InvalidUpgradeAuthority,
+    /// The signer is not the owner of the bridge
+    #[error("InvalidBridgeOwner")]
+    InvalidBridgeOwner,
+    /// There is no role change waiting to be accepted
+    #[error("NoPendingRoleChange")]
+    NoPendingRoleChange,
+    /// The signer is not the proposed holder of the role
+    #[error("InvalidPendingRole")]
+    InvalidPendingRole,
+    /// The role change was not accepted in time
+    #[error("RoleChangeExpired")]
+    RoleChangeExpired,
+    /// The multisig signers or threshold are invalid
+    #[error("InvalidMultisig")]
+    InvalidMultisig,
+    /// The signer is not part of the multisig
+    #[error("InvalidMultisigSigner")]
+    InvalidMultisigSigner,
+    /// The proposed instruction or its accounts are longer than the maximum size
+    #[error("ProposalTooLong")]
+    ProposalTooLong,
+    /// The multisig signers changed after the proposal was created
+    #[error("ProposalOutdated")]
+    ProposalOutdated,
+    /// The proposal has already been executed
+    #[error("ProposalAlreadyExecuted")]
+    ProposalAlreadyExecuted,
+    /// The accounts passed to execute a proposal differ from the proposed accounts
+    #[error("InvalidProposalAccounts")]
+    InvalidProposalAccounts,
+    /// The proposal does not have enough approvals
+    #[error("NotEnoughApprovals")]
+    NotEnoughApprovals,
+    /// A change of this config value is already queued
+    #[error("ChangeAlreadyPending")]
+    ChangeAlreadyPending,
+    /// The timelock of the change has not elapsed yet
+    #[error("TimelockNotElapsed")]
+    TimelockNotElapsed,
+    /// The timelock of the change has elapsed, it can no longer be cancelled
+    #[error("TimelockElapsed")]
+    TimelockElapsed,
+    /// Swaps to the chain are paused
+    #[error("OutboundPaused")]
+    OutboundPaused,
+    /// Swaps from the chain are paused
+    #[error("InboundPaused")]
+    InboundPaused,
+    /// The signer is neither the pauser nor the governor of the bridge
+    #[error("InvalidPauser")]
+    InvalidPauser,
+    /// The rate limit window is shorter than its number of buckets
+    #[error("InvalidRateLimit")]
+    InvalidRateLimit,
+    /// The swap exceeds the volume limit of the chain
+    #[error("RateLimitExceeded")]
+    RateLimitExceeded,
+    /// The delay of the withdrawal has not elapsed yet
+    #[error("WithdrawalLocked")]
+    WithdrawalLocked,
+    /// The minimum swap amount is above the maximum
+    #[error("InvalidAmountBounds")]
+    InvalidAmountBounds,
+    /// The swap amount is below the minimum of the chain
+    #[error("AmountTooSmall")]
+    AmountTooSmall,
+    /// The swap amount is above the maximum of the chain
+    #[error("AmountTooLarge")]
+    AmountTooLarge,
+    /// The recipient is not a valid address on the destination chain
+    #[error("InvalidRecipient")]
+    InvalidRecipient,
+    /// The deadline of the swap has passed
+    #[error("SwapExpired")]
+    SwapExpired,
+    /// The sender already used the idempotency key
+    #[error("DuplicateSwap")]
+    DuplicateSwap,
+    /// The amount does not fit into an SPL token amount
+    #[error("AmountOverflow")]
+    AmountOverflow,
+    /// The decimals of the chain or the mint are out of range
+    #[error("InvalidDecimals")]
+    InvalidDecimals,
+    /// The amount can not be represented exactly in the decimals of the destination chain. No
+    /// longer returned, kept so that the codes of later errors do not change.
+    #[error("InexactAmount")]
+    InexactAmount,
+    /// The withdrawal would leave less than the fee floor in the bridge
+    #[error("BelowFeeFloor")]
+    BelowFeeFloor,
+    /// The fee beneficiaries or weights are invalid
+    #[error("InvalidFeeSplit")]
+    InvalidFeeSplit,
+    /// The circuit breaker of the chain holds no swap to review
+    #[error("BreakerNotTripped")]
+    BreakerNotTripped,
+    /// The governor has to approve or reject the swap that tripped the circuit breaker first
+    #[error("TrippedSwapNotReviewed")]
+    TrippedSwapNotReviewed,
+    /// The fee custody of the current token still holds fees that have to be distributed first
+    #[error("FeeCustodyNotEmpty")]
+    FeeCustodyNotEmpty,
+}
+
+impl From<Error> for ProgramError {
+    fn from(e: Error) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl<T> DecodeError<T> for Error {
+    fn type_of() -> &'static str {
+        "Bridge Error"
+    }
+}
@@ -43,6 +43,47 @@ impl PrintProgramError for Error {
             Error::InvalidOwner => msg!("Error: InvalidOwner"),
             Error::InvalidSysvar => msg!("Error: InvalidSysvar"),
             Error::InvalidChain => msg!("Error: InvalidChain"),
+            Error::InvalidGovernor => msg!("Error: InvalidGovernor"),
+            Error::SwapAlreadyProcessed => msg!("Error: SwapAlreadyProcessed"),
+            Error::BridgingPaused => msg!("Error: BridgingPaused"),
+            Error::ChainDisabled => msg!("Error: ChainDisabled"),
+            Error::InvalidSystem => msg!("Error: InvalidSystem"),
+            Error::InvalidFee => msg!("Error: InvalidFee"),
+            Error::InvalidUpgradeAuthority => msg!("Error: InvalidUpgradeAuthority"),
+            Error::InvalidBridgeOwner => msg!("Error: InvalidBridgeOwner"),
+            Error::NoPendingRoleChange => msg!("Error: NoPendingRoleChange"),
+            Error::InvalidPendingRole => msg!("Error: InvalidPendingRole"),
+            Error::RoleChangeExpired => msg!("Error: RoleChangeExpired"),
+            Error::InvalidMultisig => msg!("Error: InvalidMultisig"),
+            Error::InvalidMultisigSigner => msg!("Error: InvalidMultisigSigner"),
+            Error::ProposalTooLong => msg!("Error: ProposalTooLong"),
+            Error::ProposalOutdated => msg!("Error: ProposalOutdated"),
+            Error::ProposalAlreadyExecuted => msg!("Error: ProposalAlreadyExecuted"),
+            Error::InvalidProposalAccounts => msg!("Error: InvalidProposalAccounts"),
+            Error::NotEnoughApprovals => msg!("Error: NotEnoughApprovals"),
+            Error::ChangeAlreadyPending => msg!("Error: ChangeAlreadyPending"),
+            Error::TimelockNotElapsed => msg!("Error: TimelockNotElapsed"),
+            Error::TimelockElapsed => msg!("Error: TimelockElapsed"),
+            Error::OutboundPaused => msg!("Error: OutboundPaused"),
+            Error::InboundPaused => msg!("Error: InboundPaused"),
+            Error::InvalidPauser => msg!("Error: InvalidPauser"),
+            Error::InvalidRateLimit => msg!("Error: InvalidRateLimit"),
+            Error::RateLimitExceeded => msg!("Error: RateLimitExceeded"),
+            Error::WithdrawalLocked => msg!("Error: WithdrawalLocked"),
+            Error::InvalidAmountBounds => msg!("Error: InvalidAmountBounds"),
+            Error::AmountTooSmall => msg!("Error: AmountTooSmall"),
+            Error::AmountTooLarge => msg!("Error: AmountTooLarge"),
+            Error::InvalidRecipient => msg!("Error: InvalidRecipient"),
+            Error::SwapExpired => msg!("Error: SwapExpired"),
+            Error::DuplicateSwap => msg!("Error: DuplicateSwap"),
+            Error::AmountOverflow => msg!("Error: AmountOverflow"),
+            Error::InvalidDecimals => msg!("Error: InvalidDecimals"),
+            Error::InexactAmount => msg!("Error: InexactAmount"),
+            Error::BelowFeeFloor => msg!("Error: BelowFeeFloor"),
+            Error::InvalidFeeSplit => msg!("Error: InvalidFeeSplit"),
+            Error::BreakerNotTripped => msg!("Error: BreakerNotTripped"),
+            Error::TrippedSwapNotReviewed => msg!("Error: TrippedSwapNotReviewed"),
+            Error::FeeCustodyNotEmpty => msg!("Error: FeeCustodyNotEmpty"),
         }
     }
 }
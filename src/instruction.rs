@@ -1,8 +1,12 @@
 #![allow(clippy::too_many_arguments)]
 //! Instruction types
 
-use std::mem::size_of;
+use std::{
+    io::{self, Cursor, Read, Write},
+    mem::size_of,
+};
 
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use primitive_types::U256;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
@@ -27,21 +31,87 @@ use crate::{
         Swap,
         FeeCalculation,
         SwapBack,
-
+        SetChainEnabled,
+        SetChainFee,
+        ClearChainFee,
+        SetDefaultProcessedFee,
+        ChangeOwner,
+        AcceptGovernor,
+        AcceptOwner,
+        CancelGovernorChange,
+        CancelOwnerChange,
+        SetMultisig,
+        ProposeAdminAction,
+        ApproveProposal,
+        ExecuteProposal,
+        SetTimelockDelay,
+        ExecuteConfigChange,
+        CancelConfigChange,
+        SetChainPaused,
+        SetPauser,
+        RevokePauser,
+        SetRateLimit,
+        SetCircuitBreaker,
+        SetWithdrawalDelay,
+        ClaimPendingWithdrawal,
+        CancelPendingWithdrawal,
+        SetSwapBounds,
+        GetSwapBounds,
+        SetChainAddressFormat,
+        SetChainDecimals,
+        SetFeeFloor,
+        WithdrawFees,
+        SetFeeSplit,
+        DistributeFees,
+        SetRelayerRefund,
+        ReviewTrippedSwap,
+    },
+    state::{
+        AddressFormat, Bridge, BridgeConfig, BridgeFee, ChainFee, ConfigChange,
+        FeeBeneficiary,
+        PendingWithdrawal, SwapDirection,
     },
-    state::{Bridge, BridgeConfig},
 };
 
+/// Maximum number of signers of the multisig governor
+pub const MAX_MULTISIG_SIGNERS: usize = 10;
+/// Maximum size of an instruction proposed to the multisig governor
+pub const MAX_PROPOSAL_DATA: usize = 512;
+/// Maximum number of accounts of an instruction proposed to the multisig governor
+pub const MAX_PROPOSAL_ACCOUNTS: usize = 16;
+/// Maximum number of token accounts the fees are split between, next to the treasury
+pub const MAX_FEE_BENEFICIARIES: usize = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_snake_case)]
+pub struct InitializePayload {
+    pub croge_program: Pubkey,
+    pub system: Pubkey,
+    pub governor: Pubkey,
+    pub bridgeFeesAddress: Pubkey,
+    pub owner: Pubkey,
+    pub bridge_fee: BridgeFee,
+    pub processed_fee: u64,
+}
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_snake_case)]
 pub struct SwapPayload {
     pub amount: U256,
     pub toChainID: u32,
+    /// address of the recipient on the destination chain, in the format of the chain
+    pub recipient: [u8; 32],
+    /// key chosen by the sender so that a resubmitted swap is rejected, zero means none
+    pub idempotency_key: u64,
+    /// unix timestamp after which the swap is rejected, zero means none
+    pub deadline: i64,
 }
 
 #[repr(C)]
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(non_snake_case)]
 pub struct SwapBackPayload {
     pub to: Pubkey,
     pub amount: U256,
@@ -49,227 +119,1010 @@ pub struct SwapBackPayload {
     pub fromChainID: u32,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultisigPayload {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeSplitPayload {
+    pub treasury_weight: u16,
+    pub beneficiaries: Vec<FeeBeneficiary>,
+}
+
 /// Instructions supported by the SwapInfo program.
+///
+/// Every instruction is encoded as a one byte opcode followed by its fields in declaration
+/// order, without padding:
+/// * `U256` - 32 bytes, big endian (the encoding used by the EVM side)
+/// * `Pubkey` - 32 bytes, as returned by `Pubkey::to_bytes`
+/// * `u8`, `u16`, `u32`, `u64`, `i64` - 1, 2, 4, 8 and 8 bytes, little endian
+/// * `BridgeFee` - `bps`, `min_fee` and `max_fee` as above
+/// * `ChainFee` - `bps`, `has_bps`, `processed_fee` and `has_processed_fee` as above
+/// * `SwapDirection` - 1 byte, 0 for outbound and 1 for inbound
+/// * `ConfigChange` - 1 byte, the value of the variant
+/// * `AddressFormat` - 1 byte, the value of the variant
+/// * `[u8; 32]` - 32 bytes, as is
+/// * `Vec<Pubkey>` - 1 byte length followed by the keys
+/// * `Vec<FeeBeneficiary>` - 1 byte length followed by the `account` and `weight` of each
+/// * `Vec<AccountMeta>` - 1 byte length followed by the `pubkey`, `is_signer` and `is_writable`
+///   of each
+/// * `Vec<u8>` - 2 bytes length, little endian, followed by the bytes
+/// * `bool` - 1 byte, either 0 or 1
+///
+/// The opcodes are part of the wire format and must never be reassigned. Opcodes 0 to 14 cover
+/// the fifteen original instructions from `Initialize` through `SwapBack`; `SetChainEnabled` came
+/// with the per-chain state accounts and takes the next free opcode, 15.
+///
+/// | opcode | instruction               | opcode | instruction               |
+/// |--------|---------------------------|--------|---------------------------|
+/// | 0      | `Initialize`              | 8      | `SetBridgeFeesAddress`    |
+/// | 1      | `Swap`                    | 9      | `SetSystem`               |
+/// | 2      | `SwapBack`                | 10     | `SetProcessedFess`        |
+/// | 3      | `UpdateCrogeProgram`      | 11     | `GetProcessedFees`        |
+/// | 4      | `ExcludeFromFees`         | 12     | `GetBridgeStatus`         |
+/// | 5      | `ChangeGovernor`          | 13     | `UpdateBridgingStaus`     |
+/// | 6      | `SetBridgeFee`            | 14     | `FeeCalculation`          |
+/// | 7      | `GetBridgeFee`            | 15     | `SetChainEnabled`         |
+/// | 16     | `SetChainFee`             | 24     | `SetMultisig`             |
+/// | 17     | `ClearChainFee`           | 25     | `ProposeAdminAction`      |
+/// | 18     | `SetDefaultProcessedFee`  | 26     | `ApproveProposal`         |
+/// | 19     | `ChangeOwner`             | 27     | `ExecuteProposal`         |
+/// | 20     | `AcceptGovernor`          | 28     | `SetTimelockDelay`        |
+/// | 21     | `AcceptOwner`             | 29     | `ExecuteConfigChange`     |
+/// | 22     | `CancelGovernorChange`    | 30     | `CancelConfigChange`      |
+/// | 23     | `CancelOwnerChange`       | 31     | `SetChainPaused`          |
+/// | 32     | `SetPauser`               | 40     | `GetSwapBounds`           |
+/// | 33     | `RevokePauser`            | 41     | `SetChainAddressFormat`   |
+/// | 34     | `SetRateLimit`            | 42     | `SetChainDecimals`        |
+/// | 35     | `SetCircuitBreaker`       | 43     | `SetFeeFloor`             |
+/// | 36     | `SetWithdrawalDelay`      | 44     | `WithdrawFees`            |
+/// | 37     | `ClaimPendingWithdrawal`  | 45     | `SetFeeSplit`             |
+/// | 38     | `CancelPendingWithdrawal` | 46     | `DistributeFees`          |
+/// | 39     | `SetSwapBounds`           | 47     | `SetRelayerRefund`        |
+/// | 48     | `ReviewTrippedSwap`       |        |                           |
 #[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum BridgeInstruction {
-    Initialize(),
+    /// Creates the bridge config with all roles set. Can only be called once, by the upgrade
+    /// authority of the program.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[writable, derived]` Bridge config
+    /// 2. `[derived]` Program data account of this program
+    /// 3. `[writable, signer]` Upgrade authority, pays for the bridge config
+    Initialize(InitializePayload),
+    /// Queues a change of the token that can be swapped, see `ExecuteConfigChange`.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Pending change, no change of the value may be queued
+    /// 4. `[signer]` Governor
+    /// 5. `[writable, signer]` Payer of the pending change
     UpdateCrogeProgram(Pubkey),
+    /// Adds (`true`) or removes (`false`) the fee exemption of a wallet.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Fee exemption of the wallet
+    /// 4. `[signer]` Governor
+    /// 5. `[writable, signer]` Payer of the exemption, receives its rent when it is removed
     ExcludeFromFees(Pubkey, bool),
-    SetBridgeFee(U256),
+    /// Sets the fee the bridge keeps from inbound swaps.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetBridgeFee(BridgeFee),
+    /// Proposes a new governor. The change takes effect once the new governor accepts it with
+    /// `AcceptGovernor`, which has to happen within `PendingRole::EXPIRY` seconds.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[writable]` Bridge config
+    /// 2. `[signer]` Owner
     ChangeGovernor(Pubkey),
+    /// Logs the fee the bridge keeps from inbound swaps.
+    ///
+    /// 0. `[]` Bridge config
     GetBridgeFee(),
-    SetBridgeFeesAddress(U256),
+    /// Queues a change of the token account that receives the bridge fee, see
+    /// `ExecuteConfigChange`.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Pending change, no change of the value may be queued
+    /// 4. `[signer]` Governor
+    /// 5. `[writable, signer]` Payer of the pending change
+    SetBridgeFeesAddress(Pubkey),
+    /// Queues a change of the authority that signs inbound swaps, see `ExecuteConfigChange`.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Pending change, no change of the value may be queued
+    /// 4. `[signer]` Governor
+    /// 5. `[writable, signer]` Payer of the pending change
     SetSystem(Pubkey),
+    /// Overrides the lamport fee of outbound swaps to a chain, in the range of a `u64`. This is
+    /// the outbound processed fee of `SetChainFee`; the inbound one can only be set there.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Chain state, created if it does not exist
+    /// 3. `[signer]` Governor
+    /// 4. `[writable, signer]` Payer of the chain state
     SetProcessedFess(u32, U256),
+    /// Logs the lamport fees of swaps with a chain.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[derived]` Chain state
     GetProcessedFees(u32),
+    /// Logs whether the inbound swap with the given nonce and source chain was processed.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[derived]` Swap back receipt
     GetBridgeStatus(U256, u32),
+    /// Pauses (`true`) or resumes (`false`) all swaps.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor, or the pauser when pausing
     UpdateBridgingStaus(bool),
+    /// Locks Croge tokens in custody and records the swap under the next nonce of the
+    /// destination chain. The processed fee of the chain has to be transferred to the bridge
+    /// in the preceding instruction. The bridge fee goes to the fee custody account unless the
+    /// sender is excluded from fees. The recipient has to match the address format of the chain.
+    /// A swap is rejected after its deadline or if the sender already used its idempotency key.
+    /// The amount is in the decimals of the mint. What can not be represented in the decimals of
+    /// the destination chain is kept as a fee, also for senders excluded from fees.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` SPL token program
+    /// 2. `[]` Rent sysvar
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` Instructions sysvar
+    /// 5. `[writable]` Token account to take the tokens from
+    /// 6. `[]` Bridge config
+    /// 7. `[writable, derived]` Chain state of the destination chain
+    /// 8. `[writable, derived]` Swap record for the next nonce
+    /// 9. `[writable, derived]` Idempotency key of the owner, only created if the key is not zero
+    /// 10. `[]` Croge mint
+    /// 11. `[writable, derived]` Custody account, created if it does not exist
+    /// 12. `[derived]` Fee exemption of the owner of the token account
+    /// 13. `[writable, derived]` Fee custody account, created if it does not exist
+    /// 14. `[writable, signer]` Owner of the token account, pays for the swap record and the
+    ///     idempotency key
     Swap(SwapPayload),
-    FeeCalculation(U256),
+    /// Logs the fee the bridge would keep from an inbound swap of the given amount from a chain,
+    /// unless the recipient is excluded from fees. The amount is in the decimals of the chain like
+    /// in `SwapBack`, the fee in the decimals of the mint. Dust truncated by the scaling is not
+    /// part of the quote.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[derived]` Chain state of the source chain
+    FeeCalculation(u32, U256),
+    /// Pays out an inbound swap from custody. The bridge fee goes to the fee custody account
+    /// unless the owner of the recipient account is excluded from fees.
+    ///
+    /// Inbound swaps are charged a processed fee like outbound ones: the inbound
+    /// `ChainFee.processed_fee` of the source chain, set with `SetChainFee`, or else
+    /// `BridgeConfig.processed_fee`. If it is not zero it has to be transferred to the bridge
+    /// config in the preceding instruction.
+    ///
+    /// If the swap trips the circuit breaker of the chain, inbound swaps of the chain are paused
+    /// instead and the swap is left unprocessed. Once the chain is resumed it can be submitted
+    /// again and passes the breaker.
+    /// Swaps above `large_withdrawal_threshold` are not paid out but held in a pending
+    /// withdrawal, see `ClaimPendingWithdrawal`.
+    ///
+    /// The amount is in the decimals of the source chain. Dust below one unit of the mint is
+    /// collected per chain and paid as fees once it adds up to a whole unit.
+    ///
+    /// The system authority is refunded `relayer_refund` lamports from the bridge for every
+    /// processed swap, as long as the bridge stays rent exempt.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` SPL token program
+    /// 2. `[]` Rent sysvar
+    /// 3. `[]` Clock sysvar
+    /// 4. `[]` Instructions sysvar, holds the transfer of the processed fee
+    /// 5. `[]` Bridge config
+    /// 6. `[writable, derived]` Chain state of the source chain
+    /// 7. `[writable, derived]` Swap back receipt, must not exist yet
+    /// 8. `[writable, derived]` Pending withdrawal, only created for large swaps
+    /// 9. `[derived]` Fee exemption of the owner of the recipient account
+    /// 10. `[writable, derived]` Custody account
+    /// 11. `[writable]` Recipient token account `to`
+    /// 12. `[writable, derived]` Fee custody account, created if it does not exist
+    /// 13. `[writable, signer]` System authority, pays for the receipt, the pending withdrawal
+    ///     and the fee custody account
     SwapBack(SwapBackPayload),
+    /// Enables or disables swaps with a chain. A chain can only be enabled once its decimals are
+    /// set, see `SetChainDecimals`.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Chain state, created if it does not exist
+    /// 3. `[signer]` Governor
+    /// 4. `[writable, signer]` Payer of the chain state
+    SetChainEnabled(u32, bool),
+    /// Overrides the fees of a chain for one direction. Values that are not set fall back to the
+    /// bridge config.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Chain state, created if it does not exist
+    /// 3. `[signer]` Governor
+    /// 4. `[writable, signer]` Payer of the chain state
+    SetChainFee(u32, SwapDirection, ChainFee),
+    /// Removes all fee overrides of a chain for one direction.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Chain state, created if it does not exist
+    /// 3. `[writable, signer]` Governor
+    ClearChainFee(u32, SwapDirection),
+    /// Sets the lamport fee of swaps with chains that don't override it.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetDefaultProcessedFee(u64),
+    /// Proposes a new owner. The change takes effect once the new owner accepts it with
+    /// `AcceptOwner`, which has to happen within `PendingRole::EXPIRY` seconds.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[writable]` Bridge config
+    /// 2. `[signer]` Owner
+    ChangeOwner(Pubkey),
+    /// Accepts a pending governor change.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[writable]` Bridge config
+    /// 2. `[signer]` Proposed governor
+    AcceptGovernor(),
+    /// Accepts a pending owner change.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[writable]` Bridge config
+    /// 2. `[signer]` Proposed owner
+    AcceptOwner(),
+    /// Withdraws a pending governor change.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Owner
+    CancelGovernorChange(),
+    /// Withdraws a pending owner change.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Owner
+    CancelOwnerChange(),
+    /// Creates the multisig or replaces its signers, which invalidates all open proposals. The
+    /// multisig acts as the governor once its address is accepted as governor.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Multisig, created if it does not exist
+    /// 3. `[signer]` Governor
+    /// 4. `[writable, signer]` Payer of the multisig
+    SetMultisig(MultisigPayload),
+    /// Proposes a serialized admin instruction and its accounts to the multisig. The proposal is
+    /// created under the next proposal index of the multisig and counts as approved by the
+    /// proposer. Approvals cover the accounts as well, the instruction is executed with exactly
+    /// these accounts.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Multisig
+    /// 3. `[writable, derived]` Proposal
+    /// 4. `[writable, signer]` Multisig signer, pays for the proposal
+    ProposeAdminAction(Vec<u8>, Vec<AccountMeta>),
+    /// Approves the proposal with the given index.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[derived]` Multisig
+    /// 2. `[writable, derived]` Proposal
+    /// 3. `[signer]` Multisig signer
+    ApproveProposal(u64),
+    /// Executes the proposal with the given index once it has enough approvals, signing as the
+    /// multisig. Anyone can execute.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Multisig
+    /// 2. `[writable, derived]` Proposal
+    /// 3. `[]` Bridge program
+    /// 4. ..  Accounts of the proposed instruction, in the proposed order
+    ExecuteProposal(u64),
+    /// Queues a change of the delay of queued config changes, see `ExecuteConfigChange`.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Pending change, no change of the value may be queued
+    /// 4. `[signer]` Governor
+    /// 5. `[writable, signer]` Payer of the pending change
+    SetTimelockDelay(u32),
+    /// Applies a queued config change once its delay has elapsed. Anyone can execute.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[writable]` Bridge config
+    /// 2. `[writable, derived]` Pending change
+    /// 3. `[writable]` Payer of the pending change, receives its rent
+    /// 4. `[]` Fee custody account of the current token, only for `ConfigChange::CrogeProgram`.
+    ///    The token can only be changed once the fees in it are distributed.
+    ExecuteConfigChange(ConfigChange),
+    /// Drops a queued config change before its delay has elapsed.
+    ///
+    /// 0. `[]` Clock sysvar
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Pending change
+    /// 3. `[signer]` Governor
+    /// 4. `[writable]` Payer of the pending change, receives its rent
+    CancelConfigChange(ConfigChange),
+    /// Pauses (`true`) or resumes (`false`) swaps with a chain in one direction. Inbound swaps
+    /// can only be resumed once the swap that tripped the circuit breaker was reviewed, see
+    /// `ReviewTrippedSwap`.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[signer]` Governor, or the pauser when pausing
+    SetChainPaused(u32, SwapDirection, bool),
+    /// Sets the key that may pause swaps.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetPauser(Pubkey),
+    /// Removes the pauser.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    RevokePauser(),
+    /// Limits the amount swapped with a chain in one direction within a rolling window of the
+    /// given number of seconds. A maximum amount of zero removes the limit. Changing the window
+    /// resets the swapped amount.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[signer]` Governor
+    SetRateLimit(u32, SwapDirection, U256, u32),
+    /// Sets the amount of a single inbound swap and the inbound volume per hour that pause
+    /// inbound swaps of a chain. Zero disables a threshold.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[signer]` Governor
+    SetCircuitBreaker(u32, U256, U256),
+    /// Sets the amount above which inbound swaps are held back and the delay in seconds after
+    /// which they can be claimed. An amount of zero pays out all swaps immediately.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetWithdrawalDelay(U256, u32),
+    /// Pays out the held back inbound swap with the given source chain and nonce once its delay
+    /// has elapsed. Anyone can claim.
+    ///
+    /// 0. `[]` SPL token program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[derived]` Chain state of the source chain
+    /// 4. `[writable, derived]` Pending withdrawal
+    /// 5. `[writable, derived]` Custody account
+    /// 6. `[writable]` Recipient token account `to`
+    /// 7. `[writable, derived]` Fee custody account
+    /// 8. `[writable]` Payer of the pending withdrawal, receives its rent
+    ClaimPendingWithdrawal(u32, U256),
+    /// Drops the held back inbound swap with the given source chain and nonce. The tokens stay in
+    /// custody and the swap cannot be submitted again.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Pending withdrawal
+    /// 2. `[signer]` Governor
+    /// 3. `[writable]` Payer of the pending withdrawal, receives its rent
+    CancelPendingWithdrawal(u32, U256),
+    /// Sets the minimum and maximum amount of a single swap to a chain. Zero removes a bound.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[signer]` Governor
+    SetSwapBounds(u32, U256, U256),
+    /// Logs the minimum and maximum amount of a single swap to a chain.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[derived]` Chain state
+    GetSwapBounds(u32),
+    /// Sets the format that recipients of swaps to a chain have to be in.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[signer]` Governor
+    SetChainAddressFormat(u32, AddressFormat),
+    /// Sets the decimals of the token on a chain. The decimals of the mint are taken from the
+    /// mint account. Dust left over from earlier inbound swaps stays in custody.
+    ///
+    /// 0. `[]` Bridge config
+    /// 1. `[writable, derived]` Chain state
+    /// 2. `[]` Croge mint
+    /// 3. `[signer]` Governor
+    SetChainDecimals(u32, u8),
+    /// Sets the lamports that `WithdrawFees` leaves in the bridge on top of its rent exemption.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetFeeFloor(u64),
+    /// Sends lamports collected as processed fees from the bridge to the owner of the treasury
+    /// token account `bridgeFeesAddress`, which can hold lamports unlike the token account. The
+    /// bridge keeps its rent exemption plus the fee floor. Zero withdraws everything above that.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[]` `bridgeFeesAddress`
+    /// 2. `[writable]` Owner of `bridgeFeesAddress`, receives the lamports
+    /// 3. `[signer]` Governor
+    WithdrawFees(u64),
+    /// Sets the weights the token fees are split by between the treasury `bridgeFeesAddress` and
+    /// up to `MAX_FEE_BENEFICIARIES` other token accounts.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    /// 2. `[]` Token account of each beneficiary, in the order of the payload. Has to hold the
+    ///    token of `croge_program`.
+    SetFeeSplit(FeeSplitPayload),
+    /// Splits the balance of the fee custody account by the fee split of the bridge config. The
+    /// rounding remainder goes to the treasury. Anyone can distribute.
+    ///
+    /// 0. `[]` SPL token program
+    /// 1. `[]` Bridge config
+    /// 2. `[writable, derived]` Fee custody account
+    /// 3. `[writable]` Treasury token account `bridgeFeesAddress`
+    /// 4. `[writable]` Token account of each beneficiary, in the order of the fee split
+    DistributeFees(),
+    /// Sets the lamports refunded to the relayer of every inbound swap. Zero disables the refund.
+    ///
+    /// 0. `[writable]` Bridge config
+    /// 1. `[signer]` Governor
+    SetRelayerRefund(u64),
+    /// Approves or rejects the inbound swap that tripped the circuit breaker of a chain. An
+    /// approved swap passes the breaker once when it is resubmitted, a rejected swap is marked as
+    /// processed and is never paid out. Inbound swaps of the chain can only be resumed after the
+    /// review.
+    ///
+    /// 0. `[]` System program
+    /// 1. `[]` Clock sysvar
+    /// 2. `[]` Bridge config
+    /// 3. `[writable, derived]` Chain state
+    /// 4. `[writable, derived]` Receipt of the tripped swap, created when it is rejected
+    /// 5. `[writable, signer]` Governor, pays for the receipt
+    ReviewTrippedSwap(u32, bool),
 }
 
-
-
 impl BridgeInstruction {
     /// Deserializes a byte buffer into a BridgeInstruction
     pub fn deserialize(input: &[u8]) -> Result<Self, ProgramError> {
-        if input.len() < size_of::<u8>() {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        Ok(match input[0] {
-            0 =>  Initialize(),
-            1 => {
-                let payload: &SwapPayload = unpack(input)?;
+        let mut data = Cursor::new(input);
+        let instruction =
+            Self::read(&mut data).map_err(|_| ProgramError::InvalidInstructionData)?;
 
-                Swap(*payload)
-            }
-            2 => {
-                let payload: &SwapBackPayload = unpack(input)?;
+        // Trailing bytes are rejected so that every instruction has exactly one encoding
+        if data.position() != input.len() as u64 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-                SwapBack(*payload)
-            }
-            3 => {
-                let payload: &Pubkey = unpack(input)?;
+        Ok(instruction)
+    }
 
-                UpdateCrogeProgram(*payload)
+    fn read(data: &mut Cursor<&[u8]>) -> io::Result<Self> {
+        Ok(match data.read_u8()? {
+            0 => Initialize(InitializePayload {
+                croge_program: read_pubkey(data)?,
+                system: read_pubkey(data)?,
+                governor: read_pubkey(data)?,
+                bridgeFeesAddress: read_pubkey(data)?,
+                owner: read_pubkey(data)?,
+                bridge_fee: BridgeFee {
+                    bps: data.read_u16::<LittleEndian>()?,
+                    min_fee: read_u256(data)?,
+                    max_fee: read_u256(data)?,
+                },
+                processed_fee: data.read_u64::<LittleEndian>()?,
+            }),
+            1 => Swap(SwapPayload {
+                amount: read_u256(data)?,
+                toChainID: data.read_u32::<LittleEndian>()?,
+                recipient: read_bytes32(data)?,
+                idempotency_key: data.read_u64::<LittleEndian>()?,
+                deadline: data.read_i64::<LittleEndian>()?,
+            }),
+            2 => SwapBack(SwapBackPayload {
+                to: read_pubkey(data)?,
+                amount: read_u256(data)?,
+                nonce: read_u256(data)?,
+                fromChainID: data.read_u32::<LittleEndian>()?,
+            }),
+            3 => UpdateCrogeProgram(read_pubkey(data)?),
+            4 => ExcludeFromFees(read_pubkey(data)?, read_bool(data)?),
+            5 => ChangeGovernor(read_pubkey(data)?),
+            6 => SetBridgeFee(BridgeFee {
+                bps: data.read_u16::<LittleEndian>()?,
+                min_fee: read_u256(data)?,
+                max_fee: read_u256(data)?,
+            }),
+            7 => GetBridgeFee(),
+            8 => SetBridgeFeesAddress(read_pubkey(data)?),
+            9 => SetSystem(read_pubkey(data)?),
+            10 => SetProcessedFess(data.read_u32::<LittleEndian>()?, read_u256(data)?),
+            11 => GetProcessedFees(data.read_u32::<LittleEndian>()?),
+            12 => GetBridgeStatus(read_u256(data)?, data.read_u32::<LittleEndian>()?),
+            13 => UpdateBridgingStaus(read_bool(data)?),
+            14 => FeeCalculation(data.read_u32::<LittleEndian>()?, read_u256(data)?),
+            15 => SetChainEnabled(data.read_u32::<LittleEndian>()?, read_bool(data)?),
+            16 => SetChainFee(
+                data.read_u32::<LittleEndian>()?,
+                read_direction(data)?,
+                ChainFee {
+                    bps: data.read_u16::<LittleEndian>()?,
+                    has_bps: read_bool(data)?,
+                    processed_fee: data.read_u64::<LittleEndian>()?,
+                    has_processed_fee: read_bool(data)?,
+                },
+            ),
+            17 => ClearChainFee(data.read_u32::<LittleEndian>()?, read_direction(data)?),
+            18 => SetDefaultProcessedFee(data.read_u64::<LittleEndian>()?),
+            19 => ChangeOwner(read_pubkey(data)?),
+            20 => AcceptGovernor(),
+            21 => AcceptOwner(),
+            22 => CancelGovernorChange(),
+            23 => CancelOwnerChange(),
+            24 => {
+                let len = data.read_u8()?;
+                let mut signers = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    signers.push(read_pubkey(data)?);
+                }
+                SetMultisig(MultisigPayload {
+                    signers,
+                    threshold: data.read_u8()?,
+                })
             }
-            4 => {
-                let payload: &Pubkey = unpack(input)?;
-
-                UpdateCrogeProgram(*payload)
+            25 => {
+                let len = data.read_u16::<LittleEndian>()?;
+                let mut action = vec![0u8; len as usize];
+                data.read_exact(&mut action)?;
+                let len = data.read_u8()?;
+                let mut accounts = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    accounts.push(AccountMeta {
+                        pubkey: read_pubkey(data)?,
+                        is_signer: read_bool(data)?,
+                        is_writable: read_bool(data)?,
+                    });
+                }
+                ProposeAdminAction(action, accounts)
             }
-            6 => {
-                let payload: &U256 = unpack(input)?;
-
-                SetBridgeFee(*payload)
+            26 => ApproveProposal(data.read_u64::<LittleEndian>()?),
+            27 => ExecuteProposal(data.read_u64::<LittleEndian>()?),
+            28 => SetTimelockDelay(data.read_u32::<LittleEndian>()?),
+            29 => ExecuteConfigChange(read_config_change(data)?),
+            30 => CancelConfigChange(read_config_change(data)?),
+            31 => SetChainPaused(
+                data.read_u32::<LittleEndian>()?,
+                read_direction(data)?,
+                read_bool(data)?,
+            ),
+            32 => SetPauser(read_pubkey(data)?),
+            33 => RevokePauser(),
+            34 => SetRateLimit(
+                data.read_u32::<LittleEndian>()?,
+                read_direction(data)?,
+                read_u256(data)?,
+                data.read_u32::<LittleEndian>()?,
+            ),
+            35 => SetCircuitBreaker(
+                data.read_u32::<LittleEndian>()?,
+                read_u256(data)?,
+                read_u256(data)?,
+            ),
+            36 => SetWithdrawalDelay(read_u256(data)?, data.read_u32::<LittleEndian>()?),
+            37 => ClaimPendingWithdrawal(data.read_u32::<LittleEndian>()?, read_u256(data)?),
+            38 => CancelPendingWithdrawal(data.read_u32::<LittleEndian>()?, read_u256(data)?),
+            39 => SetSwapBounds(
+                data.read_u32::<LittleEndian>()?,
+                read_u256(data)?,
+                read_u256(data)?,
+            ),
+            40 => GetSwapBounds(data.read_u32::<LittleEndian>()?),
+            41 => SetChainAddressFormat(
+                data.read_u32::<LittleEndian>()?,
+                read_address_format(data)?,
+            ),
+            42 => SetChainDecimals(data.read_u32::<LittleEndian>()?, data.read_u8()?),
+            43 => SetFeeFloor(data.read_u64::<LittleEndian>()?),
+            44 => WithdrawFees(data.read_u64::<LittleEndian>()?),
+            45 => {
+                let treasury_weight = data.read_u16::<LittleEndian>()?;
+                let len = data.read_u8()?;
+                let mut beneficiaries = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    beneficiaries.push(FeeBeneficiary {
+                        account: read_pubkey(data)?,
+                        weight: data.read_u16::<LittleEndian>()?,
+                    });
+                }
+                SetFeeSplit(FeeSplitPayload {
+                    treasury_weight,
+                    beneficiaries,
+                })
             }
-            _ => return Err(ProgramError::InvalidInstructionData),
+            46 => DistributeFees(),
+            47 => SetRelayerRefund(data.read_u64::<LittleEndian>()?),
+            48 => ReviewTrippedSwap(data.read_u32::<LittleEndian>()?, read_bool(data)?),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "unknown opcode")),
         })
     }
 
     /// Serializes a BridgeInstruction into a byte buffer.
-    pub fn serialize(self: Self) -> Result<Vec<u8>, ProgramError> {
-        let mut output = Vec::with_capacity(size_of::<BridgeInstruction>());
+    pub fn serialize(self) -> Result<Vec<u8>, ProgramError> {
+        let mut output = Vec::with_capacity(size_of::<BridgeInstruction>() + 1);
+        self.write(&mut output)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        Ok(output)
+    }
 
+    fn write(self, v: &mut Vec<u8>) -> io::Result<()> {
         match self {
-            Self::Initialize() => {
-                output.resize(1, 0);
-                output[0] = 0;
-                #[allow(clippy::cast_ptr_alignment)]
-                    let value = unsafe {
-                    &mut *(&mut output[size_of::<u8>()] as *mut u8 as *mut InitializePayload)
-                };
+            Initialize(payload) => {
+                v.write_u8(0)?;
+                write_pubkey(v, &payload.croge_program)?;
+                write_pubkey(v, &payload.system)?;
+                write_pubkey(v, &payload.governor)?;
+                write_pubkey(v, &payload.bridgeFeesAddress)?;
+                write_pubkey(v, &payload.owner)?;
+                v.write_u16::<LittleEndian>(payload.bridge_fee.bps)?;
+                write_u256(v, payload.bridge_fee.min_fee)?;
+                write_u256(v, payload.bridge_fee.max_fee)?;
+                v.write_u64::<LittleEndian>(payload.processed_fee)?;
             }
-
-            Self::Swap(payload) => {
-                output.resize( 1, 0);
-                output[0] = 1;
-
-                if Bridge::_isBridgingPaused == true {
-                    return Err("Contract is paused now");
+            Swap(payload) => {
+                v.write_u8(1)?;
+                write_u256(v, payload.amount)?;
+                v.write_u32::<LittleEndian>(payload.toChainID)?;
+                v.write_all(&payload.recipient)?;
+                v.write_u64::<LittleEndian>(payload.idempotency_key)?;
+                v.write_i64::<LittleEndian>(payload.deadline)?;
+            }
+            SwapBack(payload) => {
+                v.write_u8(2)?;
+                write_pubkey(v, &payload.to)?;
+                write_u256(v, payload.amount)?;
+                write_u256(v, payload.nonce)?;
+                v.write_u32::<LittleEndian>(payload.fromChainID)?;
+            }
+            UpdateCrogeProgram(croge_program) => {
+                v.write_u8(3)?;
+                write_pubkey(v, &croge_program)?;
+            }
+            ExcludeFromFees(wallet, excluded) => {
+                v.write_u8(4)?;
+                write_pubkey(v, &wallet)?;
+                write_bool(v, excluded)?;
+            }
+            ChangeGovernor(governor) => {
+                v.write_u8(5)?;
+                write_pubkey(v, &governor)?;
+            }
+            SetBridgeFee(fee) => {
+                v.write_u8(6)?;
+                v.write_u16::<LittleEndian>(fee.bps)?;
+                write_u256(v, fee.min_fee)?;
+                write_u256(v, fee.max_fee)?;
+            }
+            GetBridgeFee() => {
+                v.write_u8(7)?;
+            }
+            SetBridgeFeesAddress(address) => {
+                v.write_u8(8)?;
+                write_pubkey(v, &address)?;
+            }
+            SetSystem(system) => {
+                v.write_u8(9)?;
+                write_pubkey(v, &system)?;
+            }
+            SetProcessedFess(chain_id, fee) => {
+                v.write_u8(10)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_u256(v, fee)?;
+            }
+            GetProcessedFees(chain_id) => {
+                v.write_u8(11)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+            }
+            GetBridgeStatus(nonce, from_chain_id) => {
+                v.write_u8(12)?;
+                write_u256(v, nonce)?;
+                v.write_u32::<LittleEndian>(from_chain_id)?;
+            }
+            UpdateBridgingStaus(paused) => {
+                v.write_u8(13)?;
+                write_bool(v, paused)?;
+            }
+            FeeCalculation(chain_id, amount) => {
+                v.write_u8(14)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_u256(v, amount)?;
+            }
+            SetChainEnabled(chain_id, enabled) => {
+                v.write_u8(15)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_bool(v, enabled)?;
+            }
+            SetChainFee(chain_id, direction, fee) => {
+                v.write_u8(16)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(direction as u8)?;
+                v.write_u16::<LittleEndian>(fee.bps)?;
+                write_bool(v, fee.has_bps)?;
+                v.write_u64::<LittleEndian>(fee.processed_fee)?;
+                write_bool(v, fee.has_processed_fee)?;
+            }
+            ClearChainFee(chain_id, direction) => {
+                v.write_u8(17)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(direction as u8)?;
+            }
+            SetDefaultProcessedFee(fee) => {
+                v.write_u8(18)?;
+                v.write_u64::<LittleEndian>(fee)?;
+            }
+            ChangeOwner(owner) => {
+                v.write_u8(19)?;
+                write_pubkey(v, &owner)?;
+            }
+            AcceptGovernor() => {
+                v.write_u8(20)?;
+            }
+            AcceptOwner() => {
+                v.write_u8(21)?;
+            }
+            CancelGovernorChange() => {
+                v.write_u8(22)?;
+            }
+            CancelOwnerChange() => {
+                v.write_u8(23)?;
+            }
+            SetMultisig(payload) => {
+                v.write_u8(24)?;
+                if payload.signers.len() > u8::MAX as usize {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many signers"));
                 }
-
-                let amount: U256 = payload.amount;
-                let toChainID: u32 = payload.toChainID;
-
-                let sender = Bridge::token_account_deserialize(sender_account_info)?;
-                let payment = Bridge::token_account_deserialize(payment_info)?;
-
-                if payment < Bridge::_processedFees[toChainID] {
-                   return Err("Insufficient processed fees");
+                v.write_u8(payload.signers.len() as u8)?;
+                for signer in payload.signers.iter() {
+                    write_pubkey(v, signer)?;
+                }
+                v.write_u8(payload.threshold)?;
+            }
+            ProposeAdminAction(action, accounts) => {
+                v.write_u8(25)?;
+                if action.len() > u16::MAX as usize {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "action too long"));
+                }
+                v.write_u16::<LittleEndian>(action.len() as u16)?;
+                v.write_all(&action)?;
+                if accounts.len() > u8::MAX as usize {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, "too many accounts"));
+                }
+                v.write_u8(accounts.len() as u8)?;
+                for account in accounts.iter() {
+                    write_pubkey(v, &account.pubkey)?;
+                    write_bool(v, account.is_signer)?;
+                    write_bool(v, account.is_writable)?;
+                }
+            }
+            ApproveProposal(index) => {
+                v.write_u8(26)?;
+                v.write_u64::<LittleEndian>(index)?;
+            }
+            ExecuteProposal(index) => {
+                v.write_u8(27)?;
+                v.write_u64::<LittleEndian>(index)?;
+            }
+            SetTimelockDelay(delay) => {
+                v.write_u8(28)?;
+                v.write_u32::<LittleEndian>(delay)?;
+            }
+            ExecuteConfigChange(change) => {
+                v.write_u8(29)?;
+                v.write_u8(change as u8)?;
+            }
+            CancelConfigChange(change) => {
+                v.write_u8(30)?;
+                v.write_u8(change as u8)?;
+            }
+            SetChainPaused(chain_id, direction, paused) => {
+                v.write_u8(31)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(direction as u8)?;
+                write_bool(v, paused)?;
+            }
+            SetPauser(pauser) => {
+                v.write_u8(32)?;
+                write_pubkey(v, &pauser)?;
+            }
+            RevokePauser() => {
+                v.write_u8(33)?;
+            }
+            SetRateLimit(chain_id, direction, max_amount, window) => {
+                v.write_u8(34)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(direction as u8)?;
+                write_u256(v, max_amount)?;
+                v.write_u32::<LittleEndian>(window)?;
+            }
+            SetCircuitBreaker(chain_id, max_amount, max_hourly_amount) => {
+                v.write_u8(35)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_u256(v, max_amount)?;
+                write_u256(v, max_hourly_amount)?;
+            }
+            SetWithdrawalDelay(threshold, delay) => {
+                v.write_u8(36)?;
+                write_u256(v, threshold)?;
+                v.write_u32::<LittleEndian>(delay)?;
+            }
+            ClaimPendingWithdrawal(from_chain_id, nonce) => {
+                v.write_u8(37)?;
+                v.write_u32::<LittleEndian>(from_chain_id)?;
+                write_u256(v, nonce)?;
+            }
+            CancelPendingWithdrawal(from_chain_id, nonce) => {
+                v.write_u8(38)?;
+                v.write_u32::<LittleEndian>(from_chain_id)?;
+                write_u256(v, nonce)?;
+            }
+            SetSwapBounds(chain_id, min_amount, max_amount) => {
+                v.write_u8(39)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_u256(v, min_amount)?;
+                write_u256(v, max_amount)?;
+            }
+            GetSwapBounds(chain_id) => {
+                v.write_u8(40)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+            }
+            SetChainAddressFormat(chain_id, format) => {
+                v.write_u8(41)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(format as u8)?;
+            }
+            SetChainDecimals(chain_id, decimals) => {
+                v.write_u8(42)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                v.write_u8(decimals)?;
+            }
+            SetFeeFloor(floor) => {
+                v.write_u8(43)?;
+                v.write_u64::<LittleEndian>(floor)?;
+            }
+            WithdrawFees(amount) => {
+                v.write_u8(44)?;
+                v.write_u64::<LittleEndian>(amount)?;
+            }
+            SetFeeSplit(payload) => {
+                v.write_u8(45)?;
+                v.write_u16::<LittleEndian>(payload.treasury_weight)?;
+                if payload.beneficiaries.len() > u8::MAX as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "too many beneficiaries",
+                    ));
+                }
+                v.write_u8(payload.beneficiaries.len() as u8)?;
+                for beneficiary in payload.beneficiaries.iter() {
+                    write_pubkey(v, &beneficiary.account)?;
+                    v.write_u16::<LittleEndian>(beneficiary.weight)?;
                 }
+            }
+            DistributeFees() => {
+                v.write_u8(46)?;
+            }
+            SetRelayerRefund(refund) => {
+                v.write_u8(47)?;
+                v.write_u64::<LittleEndian>(refund)?;
+            }
+            ReviewTrippedSwap(chain_id, approve) => {
+                v.write_u8(48)?;
+                v.write_u32::<LittleEndian>(chain_id)?;
+                write_bool(v, approve)?;
+            }
+        }
+        Ok(())
+    }
+}
 
-                let _nonce: U256 = Bridge::_nonces[toChainID];
-                _nonce = _nonce.add(1);
-                Bridge::_nonce[toChainID] = _nonce;
-                         
+fn read_u256(data: &mut Cursor<&[u8]>) -> io::Result<U256> {
+    let mut bytes = [0u8; 32];
+    data.read_exact(&mut bytes)?;
+    Ok(U256::from_big_endian(&bytes))
+}
 
-                let sender_account_info = next_account_info(account_info_iter)?;
-                let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-                let transfer_info = next_account_info(account_info_iter)?;
-                let payer_info = next_account_info(account_info_iter)?;
+fn read_bytes32(data: &mut Cursor<&[u8]>) -> io::Result<[u8; 32]> {
+    let mut bytes = [0u8; 32];
+    data.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
 
-                
-                let bridge_data = bridge_info.try_borrow_data()?;
-                let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
-                let clock = Clock::from_account_info(clock_info)?;
+fn read_pubkey(data: &mut Cursor<&[u8]>) -> io::Result<Pubkey> {
+    let mut bytes = [0u8; 32];
+    data.read_exact(&mut bytes)?;
+    Ok(Pubkey::new(&bytes))
+}
 
-                if *instructions_info.key != solana_program::sysvar::instructions::id() {
-                    return Err(Error::InvalidSysvar.into());
-                }
-            }
+fn read_bool(data: &mut Cursor<&[u8]>) -> io::Result<bool> {
+    match data.read_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid bool")),
+    }
+}
 
-            Self::SwapBack(payload) => {
-                output.resize( 1, 0);
-                output[0] = 1;
-                let sender = Bridge::token_account_deserialize(sender_account_info)?;
+fn read_direction(data: &mut Cursor<&[u8]>) -> io::Result<SwapDirection> {
+    match data.read_u8()? {
+        0 => Ok(SwapDirection::Outbound),
+        1 => Ok(SwapDirection::Inbound),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid direction")),
+    }
+}
 
-                if sender != Bridge::config::system {
-                    return Err("The caller is not system");
-                }
+fn read_config_change(data: &mut Cursor<&[u8]>) -> io::Result<ConfigChange> {
+    match data.read_u8()? {
+        0 => Ok(ConfigChange::System),
+        1 => Ok(ConfigChange::CrogeProgram),
+        2 => Ok(ConfigChange::BridgeFeesAddress),
+        3 => Ok(ConfigChange::TimelockDelay),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid config change")),
+    }
+}
 
-                let to: Pubkey = payload.to;
-                let amount: U256 = payload.amount;
-                let nonce: U256 = payload.nonce;
-                let fromChainID: u32 = payload.fromChainID;
+fn read_address_format(data: &mut Cursor<&[u8]>) -> io::Result<AddressFormat> {
+    match data.read_u8()? {
+        0 => Ok(AddressFormat::Bytes32),
+        1 => Ok(AddressFormat::Evm),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid address format")),
+    }
+}
 
-                if Bridge::nonceProcessed[fromChainID][nonce] == true {
-                    return Err("Swap is already proceeds");
-                }
+fn write_u256(v: &mut Vec<u8>, value: U256) -> io::Result<()> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    v.write_all(&bytes)
+}
 
-                Bridge::nonceProcessed[fromChainID][nonce] = true;
+fn write_pubkey(v: &mut Vec<u8>, value: &Pubkey) -> io::Result<()> {
+    v.write_all(&value.to_bytes())
+}
 
-                let mut temp: U256;
-                if Bridge::_isExcludedFromFees[to] == true {
-                    temp = amount;
-                } else {
-                    temp = Bridge::feeCalculation(amount);
-                }
-                let fees: U256 = amount.sub(temp);
-
-                if fees > 0 {
-                    Self::Transfer({
-                        token: Bridge::config::croge_program,
-                        to: Bridge::config::bridgeFeesAddress,
-                        amount: fees
-                    })
-                } 
-
-                Self::Transfer({
-                    token: Bridge::config::croge_program,
-                    to: to,
-                    amount: temp
-                })
-        
-                Ok(())
-            }
-
-            Self::Transfer(payload) => {
-                output.resize(size_of::<TransferOutPayloadRaw>() + 1, 0);
-                output[0] = 1;
-                #[allow(clippy::cast_ptr_alignment)]
-                    let value = unsafe {
-                    &mut *(&mut output[size_of::<u8>()] as *mut u8 as *mut TransferOutPayloadRaw)
-                };
-
-                let mut amount_bytes = [0u8; 32];
-                payload.amount.to_big_endian(&mut amount_bytes);
-
-                *value = TransferOutPayloadRaw {
-                    amount: amount_bytes,
-                    chain_id: payload.chain_id,
-                    asset: payload.asset,
-                    target: payload.target,
-                    nonce: payload.nonce,
-                };
-            }
-           
-            
-            Self::CreateWrapped(payload) => {
-                output.resize(size_of::<AssetMeta>() + 1, 0);
-                output[0] = 7;
-                #[allow(clippy::cast_ptr_alignment)]
-                    let value =
-                    unsafe { &mut *(&mut output[size_of::<u8>()] as *mut u8 as *mut AssetMeta) };
-                *value = payload;
-            }
-        }
-        Ok(output)
-    }
+fn write_bool(v: &mut Vec<u8>, value: bool) -> io::Result<()> {
+    v.write_u8(value as u8)
 }
 
 /// Creates an 'initialize' instruction.
 #[cfg(not(target_arch = "bpf"))]
 pub fn initialize(
     program_id: &Pubkey,
-    sender: &Pubkey,
-    initial_guardian: Vec<[u8; 20]>,
-    config: &BridgeConfig,
+    upgrade_authority: &Pubkey,
+    t: &InitializePayload,
 ) -> Result<Instruction, ProgramError> {
-    if initial_guardian.len() > MAX_LEN_GUARDIAN_KEYS {
-        return Err(ProgramError::InvalidArgument);
-    }
-    let mut initial_g = [[0u8; 20]; MAX_LEN_GUARDIAN_KEYS];
-    for (i, key) in initial_guardian.iter().enumerate() {
-        initial_g[i] = *key;
-    }
-    let data = BridgeInstruction::Initialize(InitializePayload {
-        config: *config,
-        len_guardians: initial_guardian.len() as u8,
-        initial_guardian: initial_g,
-    })
-        .serialize()?;
+    let data = BridgeInstruction::Initialize(*t).serialize()?;
 
     let bridge_key = Bridge::derive_bridge_id(program_id)?;
-    let guardian_set_key = Bridge::derive_guardian_set_id(program_id, &bridge_key, 0)?;
+    let (program_data_key, _) = Pubkey::find_program_address(
+        &[program_id.as_ref()],
+        &solana_program::bpf_loader_upgradeable::id(),
+    );
 
     let accounts = vec![
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
-        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
         AccountMeta::new(bridge_key, false),
-        AccountMeta::new(guardian_set_key, false),
-        AccountMeta::new(*sender, true),
+        AccountMeta::new_readonly(program_data_key, false),
+        AccountMeta::new(*upgrade_authority, true),
     ];
 
     Ok(Instruction {
@@ -279,31 +1132,29 @@ pub fn initialize(
     })
 }
 
-/// Creates an 'TransferOut' instruction.
+/// Creates an 'Swap' instruction. `nonce` has to be the nonce the swap will be recorded under,
+/// which is the current nonce of the destination chain plus one.
 #[cfg(not(target_arch = "bpf"))]
-pub fn transfer_out(
+pub fn swap(
     program_id: &Pubkey,
-    payer: &Pubkey,
+    owner: &Pubkey,
     token_account: &Pubkey,
     token_mint: &Pubkey,
-    t: &TransferOutPayload,
+    nonce: U256,
+    t: &SwapPayload,
 ) -> Result<Instruction, ProgramError> {
-    let data = BridgeInstruction::TransferOut(*t).serialize()?;
+    let data = BridgeInstruction::Swap(*t).serialize()?;
 
     let bridge_key = Bridge::derive_bridge_id(program_id)?;
-    let transfer_key = Bridge::derive_transfer_id(
-        program_id,
-        &bridge_key,
-        t.asset.chain,
-        t.asset.address,
-        t.chain_id,
-        t.target,
-        token_account.to_bytes(),
-        t.nonce,
-    )?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, t.toChainID)?;
+    let swap_key = Bridge::derive_swap_record_id(program_id, &bridge_key, t.toChainID, nonce)?;
+    let idempotency_key =
+        Bridge::derive_swap_idempotency_id(program_id, &bridge_key, owner, t.idempotency_key)?;
+    let custody_key = Bridge::derive_custody_id(program_id, &bridge_key, token_mint)?;
+    let exemption_key = Bridge::derive_fee_exemption_id(program_id, &bridge_key, owner)?;
+    let fee_custody_key = Bridge::derive_fee_custody_id(program_id, &bridge_key, token_mint)?;
 
-    let mut accounts = vec![
-        AccountMeta::new_readonly(*program_id, false),
+    let accounts = vec![
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
         AccountMeta::new_readonly(spl_token::id(), false),
         AccountMeta::new_readonly(solana_program::sysvar::rent::id(), false),
@@ -311,15 +1162,218 @@ pub fn transfer_out(
         AccountMeta::new_readonly(solana_program::sysvar::instructions::id(), false),
         AccountMeta::new(*token_account, false),
         AccountMeta::new_readonly(bridge_key, false),
-        AccountMeta::new(transfer_key, false),
-        AccountMeta::new(*token_mint, false),
-        AccountMeta::new(*payer, true),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new(swap_key, false),
+        AccountMeta::new(idempotency_key, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new(custody_key, false),
+        AccountMeta::new_readonly(exemption_key, false),
+        AccountMeta::new(fee_custody_key, false),
+        AccountMeta::new(*owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SwapBack' instruction. `recipient` is the wallet owning the `t.to` token account.
+#[cfg(not(target_arch = "bpf"))]
+pub fn swap_back(
+    program_id: &Pubkey,
+    system: &Pubkey,
+    recipient: &Pubkey,
+    config: &BridgeConfig,
+    t: &SwapBackPayload,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SwapBack(*t).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, t.fromChainID)?;
+    let receipt_key =
+        Bridge::derive_swap_back_receipt_id(program_id, &bridge_key, t.fromChainID, t.nonce)?;
+    let withdrawal_key =
+        Bridge::derive_pending_withdrawal_id(program_id, &bridge_key, t.fromChainID, t.nonce)?;
+    let exemption_key = Bridge::derive_fee_exemption_id(program_id, &bridge_key, recipient)?;
+    let custody_key = Bridge::derive_custody_id(program_id, &bridge_key, &config.croge_program)?;
+    let fee_custody_key =
+        Bridge::derive_fee_custody_id(program_id, &bridge_key, &config.croge_program)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::rent::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::instructions::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new(receipt_key, false),
+        AccountMeta::new(withdrawal_key, false),
+        AccountMeta::new_readonly(exemption_key, false),
+        AccountMeta::new(custody_key, false),
+        AccountMeta::new(t.to, false),
+        AccountMeta::new(fee_custody_key, false),
+        AccountMeta::new(*system, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetWithdrawalDelay' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_withdrawal_delay(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    threshold: U256,
+    delay: u32,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetWithdrawalDelay(threshold, delay).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetFeeFloor' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_fee_floor(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    floor: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetFeeFloor(floor).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetRelayerRefund' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_relayer_refund(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    refund: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetRelayerRefund(refund).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'WithdrawFees' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn withdraw_fees(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    config: &BridgeConfig,
+    treasury_owner: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::WithdrawFees(amount).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(config.bridgeFeesAddress, false),
+        AccountMeta::new(*treasury_owner, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetFeeSplit' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_fee_split(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    treasury_weight: u16,
+    beneficiaries: Vec<FeeBeneficiary>,
+) -> Result<Instruction, ProgramError> {
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let mut accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
     ];
+    for beneficiary in beneficiaries.iter() {
+        accounts.push(AccountMeta::new_readonly(beneficiary.account, false));
+    }
+
+    let data = BridgeInstruction::SetFeeSplit(FeeSplitPayload {
+        treasury_weight,
+        beneficiaries,
+    })
+    .serialize()?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'DistributeFees' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn distribute_fees(
+    program_id: &Pubkey,
+    config: &BridgeConfig,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::DistributeFees().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let fee_custody_key =
+        Bridge::derive_fee_custody_id(program_id, &bridge_key, &config.croge_program)?;
 
-    // If the token is a native solana token add a custody account
-    if t.asset.chain == CHAIN_ID_SOLANA {
-        let custody_key = Bridge::derive_custody_id(program_id, &bridge_key, token_mint)?;
-        accounts.push(AccountMeta::new(custody_key, false));
+    let mut accounts = vec![
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(fee_custody_key, false),
+        AccountMeta::new(config.bridgeFeesAddress, false),
+    ];
+    let split = &config.fee_split;
+    for beneficiary in split.beneficiaries[..split.len_beneficiaries as usize].iter() {
+        accounts.push(AccountMeta::new(beneficiary.account, false));
     }
 
     Ok(Instruction {
@@ -329,3 +1383,1315 @@ pub fn transfer_out(
     })
 }
 
+/// Creates an 'ClaimPendingWithdrawal' instruction. `withdrawal` is the pending withdrawal as
+/// stored on chain.
+#[cfg(not(target_arch = "bpf"))]
+pub fn claim_pending_withdrawal(
+    program_id: &Pubkey,
+    config: &BridgeConfig,
+    withdrawal: &PendingWithdrawal,
+) -> Result<Instruction, ProgramError> {
+    let data =
+        BridgeInstruction::ClaimPendingWithdrawal(withdrawal.from_chain_id, withdrawal.nonce)
+            .serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key =
+        Bridge::derive_chain_state_id(program_id, &bridge_key, withdrawal.from_chain_id)?;
+    let withdrawal_key = Bridge::derive_pending_withdrawal_id(
+        program_id,
+        &bridge_key,
+        withdrawal.from_chain_id,
+        withdrawal.nonce,
+    )?;
+    let custody_key = Bridge::derive_custody_id(program_id, &bridge_key, &config.croge_program)?;
+    let fee_custody_key =
+        Bridge::derive_fee_custody_id(program_id, &bridge_key, &config.croge_program)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(chain_key, false),
+        AccountMeta::new(withdrawal_key, false),
+        AccountMeta::new(custody_key, false),
+        AccountMeta::new(withdrawal.to, false),
+        AccountMeta::new(fee_custody_key, false),
+        AccountMeta::new(withdrawal.payer, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'CancelPendingWithdrawal' instruction. `withdrawal` is the pending withdrawal as
+/// stored on chain.
+#[cfg(not(target_arch = "bpf"))]
+pub fn cancel_pending_withdrawal(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    withdrawal: &PendingWithdrawal,
+) -> Result<Instruction, ProgramError> {
+    let data =
+        BridgeInstruction::CancelPendingWithdrawal(withdrawal.from_chain_id, withdrawal.nonce)
+            .serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let withdrawal_key = Bridge::derive_pending_withdrawal_id(
+        program_id,
+        &bridge_key,
+        withdrawal.from_chain_id,
+        withdrawal.nonce,
+    )?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(withdrawal_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(withdrawal.payer, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetBridgeFee' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_bridge_fee(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    fee: &BridgeFee,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetBridgeFee(*fee).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'FeeCalculation' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn fee_calculation(
+    program_id: &Pubkey,
+    chain_id: u32,
+    amount: U256,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::FeeCalculation(chain_id, amount).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(chain_key, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetChainFee' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_chain_fee(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    chain_id: u32,
+    direction: SwapDirection,
+    fee: &ChainFee,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetChainFee(chain_id, direction, *fee).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ClearChainFee' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn clear_chain_fee(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    direction: SwapDirection,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ClearChainFee(chain_id, direction).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetDefaultProcessedFee' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_default_processed_fee(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    fee: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetDefaultProcessedFee(fee).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'UpdateCrogeProgram' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn update_croge_program(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    croge_program: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::UpdateCrogeProgram(*croge_program).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key =
+        Bridge::derive_pending_change_id(program_id, &bridge_key, ConfigChange::CrogeProgram)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetBridgeFeesAddress' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_bridge_fees_address(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    address: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetBridgeFeesAddress(*address).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key =
+        Bridge::derive_pending_change_id(program_id, &bridge_key, ConfigChange::BridgeFeesAddress)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetSystem' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_system(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    system: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetSystem(*system).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key =
+        Bridge::derive_pending_change_id(program_id, &bridge_key, ConfigChange::System)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetTimelockDelay' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_timelock_delay(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    delay: u32,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetTimelockDelay(delay).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key =
+        Bridge::derive_pending_change_id(program_id, &bridge_key, ConfigChange::TimelockDelay)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ExecuteConfigChange' instruction. `payer` has to be the payer of the pending
+/// change.
+#[cfg(not(target_arch = "bpf"))]
+pub fn execute_config_change(
+    program_id: &Pubkey,
+    config: &BridgeConfig,
+    payer: &Pubkey,
+    change: ConfigChange,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ExecuteConfigChange(change).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key = Bridge::derive_pending_change_id(program_id, &bridge_key, change)?;
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new(*payer, false),
+    ];
+    if change == ConfigChange::CrogeProgram {
+        let fee_custody_key =
+            Bridge::derive_fee_custody_id(program_id, &bridge_key, &config.croge_program)?;
+        accounts.push(AccountMeta::new_readonly(fee_custody_key, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'CancelConfigChange' instruction. `payer` has to be the payer of the pending
+/// change.
+#[cfg(not(target_arch = "bpf"))]
+pub fn cancel_config_change(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    change: ConfigChange,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::CancelConfigChange(change).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let pending_key = Bridge::derive_pending_change_id(program_id, &bridge_key, change)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(pending_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'UpdateBridgingStaus' instruction. `authority` is the governor or, when pausing,
+/// the pauser.
+#[cfg(not(target_arch = "bpf"))]
+pub fn update_bridging_status(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    paused: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::UpdateBridgingStaus(paused).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetPauser' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_pauser(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    pauser: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetPauser(*pauser).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'RevokePauser' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn revoke_pauser(program_id: &Pubkey, governor: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::RevokePauser().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetMultisig' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_multisig(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetMultisig(MultisigPayload { signers, threshold }).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let multisig_key = Bridge::derive_multisig_id(program_id, &bridge_key)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(multisig_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ProposeAdminAction' instruction. `index` has to be the current proposal count of
+/// the multisig and `action` an instruction created for the multisig as governor.
+#[cfg(not(target_arch = "bpf"))]
+pub fn propose_admin_action(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    index: u64,
+    action: &Instruction,
+) -> Result<Instruction, ProgramError> {
+    let data =
+        BridgeInstruction::ProposeAdminAction(action.data.clone(), action.accounts.clone())
+            .serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let multisig_key = Bridge::derive_multisig_id(program_id, &bridge_key)?;
+    let proposal_key = Bridge::derive_proposal_id(program_id, &bridge_key, index)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(multisig_key, false),
+        AccountMeta::new(proposal_key, false),
+        AccountMeta::new(*signer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ApproveProposal' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn approve_proposal(
+    program_id: &Pubkey,
+    signer: &Pubkey,
+    index: u64,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ApproveProposal(index).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let multisig_key = Bridge::derive_multisig_id(program_id, &bridge_key)?;
+    let proposal_key = Bridge::derive_proposal_id(program_id, &bridge_key, index)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(multisig_key, false),
+        AccountMeta::new(proposal_key, false),
+        AccountMeta::new_readonly(*signer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ExecuteProposal' instruction. `action` has to be the proposed instruction, its
+/// accounts are passed on to it.
+#[cfg(not(target_arch = "bpf"))]
+pub fn execute_proposal(
+    program_id: &Pubkey,
+    index: u64,
+    action: &Instruction,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ExecuteProposal(index).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let multisig_key = Bridge::derive_multisig_id(program_id, &bridge_key)?;
+    let proposal_key = Bridge::derive_proposal_id(program_id, &bridge_key, index)?;
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(multisig_key, false),
+        AccountMeta::new(proposal_key, false),
+        AccountMeta::new_readonly(*program_id, false),
+    ];
+    // The multisig signs inside the program
+    accounts.extend(action.accounts.iter().map(|meta| AccountMeta {
+        is_signer: meta.is_signer && meta.pubkey != multisig_key,
+        ..meta.clone()
+    }));
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ChangeGovernor' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn change_governor(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    new_governor: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ChangeGovernor(*new_governor).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ChangeOwner' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn change_owner(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    new_owner: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ChangeOwner(*new_owner).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'AcceptGovernor' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn accept_governor(program_id: &Pubkey, new_governor: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::AcceptGovernor().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*new_governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'AcceptOwner' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn accept_owner(program_id: &Pubkey, new_owner: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::AcceptOwner().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*new_owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'CancelGovernorChange' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn cancel_governor_change(program_id: &Pubkey, owner: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::CancelGovernorChange().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'CancelOwnerChange' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn cancel_owner_change(program_id: &Pubkey, owner: &Pubkey) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::CancelOwnerChange().serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(bridge_key, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetChainEnabled' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_chain_enabled(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    chain_id: u32,
+    enabled: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetChainEnabled(chain_id, enabled).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetChainPaused' instruction. `authority` is the governor or, when pausing, the
+/// pauser.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_chain_paused(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    chain_id: u32,
+    direction: SwapDirection,
+    paused: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetChainPaused(chain_id, direction, paused).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*authority, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetRateLimit' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_rate_limit(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    direction: SwapDirection,
+    max_amount: U256,
+    window: u32,
+) -> Result<Instruction, ProgramError> {
+    let data =
+        BridgeInstruction::SetRateLimit(chain_id, direction, max_amount, window).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetCircuitBreaker' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_circuit_breaker(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    max_amount: U256,
+    max_hourly_amount: U256,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetCircuitBreaker(chain_id, max_amount, max_hourly_amount)
+        .serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ReviewTrippedSwap' instruction. `nonce` is the nonce of the swap that tripped the
+/// circuit breaker.
+#[cfg(not(target_arch = "bpf"))]
+pub fn review_tripped_swap(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    nonce: U256,
+    approve: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ReviewTrippedSwap(chain_id, approve).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+    let receipt_key =
+        Bridge::derive_swap_back_receipt_id(program_id, &bridge_key, chain_id, nonce)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new(receipt_key, false),
+        AccountMeta::new(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetSwapBounds' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_swap_bounds(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    min_amount: U256,
+    max_amount: U256,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetSwapBounds(chain_id, min_amount, max_amount).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'GetSwapBounds' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn get_swap_bounds(program_id: &Pubkey, chain_id: u32) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::GetSwapBounds(chain_id).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(chain_key, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetChainAddressFormat' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_chain_address_format(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    chain_id: u32,
+    format: AddressFormat,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetChainAddressFormat(chain_id, format).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetChainDecimals' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_chain_decimals(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    config: &BridgeConfig,
+    chain_id: u32,
+    decimals: u8,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetChainDecimals(chain_id, decimals).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(config.croge_program, false),
+        AccountMeta::new_readonly(*governor, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'SetProcessedFess' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn set_processed_fees(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    chain_id: u32,
+    fee: U256,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::SetProcessedFess(chain_id, fee).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(chain_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'GetProcessedFees' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn get_processed_fees(program_id: &Pubkey, chain_id: u32) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::GetProcessedFees(chain_id).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let chain_key = Bridge::derive_chain_state_id(program_id, &bridge_key, chain_id)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(chain_key, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'GetBridgeStatus' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn get_bridge_status(
+    program_id: &Pubkey,
+    nonce: U256,
+    from_chain_id: u32,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::GetBridgeStatus(nonce, from_chain_id).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let receipt_key =
+        Bridge::derive_swap_back_receipt_id(program_id, &bridge_key, from_chain_id, nonce)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new_readonly(receipt_key, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'ExcludeFromFees' instruction.
+#[cfg(not(target_arch = "bpf"))]
+pub fn exclude_from_fees(
+    program_id: &Pubkey,
+    governor: &Pubkey,
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    excluded: bool,
+) -> Result<Instruction, ProgramError> {
+    let data = BridgeInstruction::ExcludeFromFees(*wallet, excluded).serialize()?;
+
+    let bridge_key = Bridge::derive_bridge_id(program_id)?;
+    let exemption_key = Bridge::derive_fee_exemption_id(program_id, &bridge_key, wallet)?;
+
+    let accounts = vec![
+        AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(bridge_key, false),
+        AccountMeta::new(exemption_key, false),
+        AccountMeta::new_readonly(*governor, true),
+        AccountMeta::new(*payer, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> Pubkey {
+        Pubkey::new(&[seed; 32])
+    }
+
+    /// One instance of every instruction together with its opcode
+    fn all_instructions() -> Vec<(u8, BridgeInstruction)> {
+        vec![
+            (
+                0,
+                Initialize(InitializePayload {
+                    croge_program: key(1),
+                    system: key(2),
+                    governor: key(3),
+                    bridgeFeesAddress: key(4),
+                    owner: key(5),
+                    bridge_fee: BridgeFee {
+                        bps: 10,
+                        min_fee: U256::from(100),
+                        max_fee: U256::zero(),
+                    },
+                    processed_fee: 5000,
+                }),
+            ),
+            (
+                1,
+                Swap(SwapPayload {
+                    amount: U256::max_value(),
+                    toChainID: 56,
+                    recipient: [7u8; 32],
+                    idempotency_key: u64::MAX,
+                    deadline: 1_700_000_000,
+                }),
+            ),
+            (
+                2,
+                SwapBack(SwapBackPayload {
+                    to: key(7),
+                    amount: U256::from(1_000_000u64),
+                    nonce: U256::from(u64::MAX) + 1,
+                    fromChainID: u32::MAX,
+                }),
+            ),
+            (3, UpdateCrogeProgram(key(1))),
+            (4, ExcludeFromFees(key(2), true)),
+            (5, ChangeGovernor(key(3))),
+            (
+                6,
+                SetBridgeFee(BridgeFee {
+                    bps: 250,
+                    min_fee: U256::from(10),
+                    max_fee: U256::max_value(),
+                }),
+            ),
+            (7, GetBridgeFee()),
+            (8, SetBridgeFeesAddress(key(4))),
+            (9, SetSystem(key(5))),
+            (10, SetProcessedFess(1, U256::from(5000))),
+            (11, GetProcessedFees(1)),
+            (12, GetBridgeStatus(U256::from(42), 97)),
+            (13, UpdateBridgingStaus(true)),
+            (14, FeeCalculation(56, U256::zero())),
+            (15, SetChainEnabled(56, false)),
+            (
+                16,
+                SetChainFee(
+                    56,
+                    SwapDirection::Inbound,
+                    ChainFee {
+                        bps: 30,
+                        has_bps: true,
+                        processed_fee: u64::MAX,
+                        has_processed_fee: false,
+                    },
+                ),
+            ),
+            (17, ClearChainFee(1, SwapDirection::Outbound)),
+            (18, SetDefaultProcessedFee(10_000)),
+            (19, ChangeOwner(key(6))),
+            (20, AcceptGovernor()),
+            (21, AcceptOwner()),
+            (22, CancelGovernorChange()),
+            (23, CancelOwnerChange()),
+            (
+                24,
+                SetMultisig(MultisigPayload {
+                    signers: vec![key(1), key(2), key(3)],
+                    threshold: 2,
+                }),
+            ),
+            (
+                25,
+                ProposeAdminAction(
+                    SetSystem(key(8)).serialize().unwrap(),
+                    vec![
+                        AccountMeta::new(key(1), false),
+                        AccountMeta::new_readonly(key(2), true),
+                    ],
+                ),
+            ),
+            (26, ApproveProposal(3)),
+            (27, ExecuteProposal(u64::MAX)),
+            (28, SetTimelockDelay(86400)),
+            (29, ExecuteConfigChange(ConfigChange::BridgeFeesAddress)),
+            (30, CancelConfigChange(ConfigChange::TimelockDelay)),
+            (31, SetChainPaused(97, SwapDirection::Outbound, true)),
+            (32, SetPauser(key(9))),
+            (33, RevokePauser()),
+            (
+                34,
+                SetRateLimit(56, SwapDirection::Inbound, U256::from(1_000_000u64), 86400),
+            ),
+            (35, SetCircuitBreaker(56, U256::from(50_000), U256::zero())),
+            (36, SetWithdrawalDelay(U256::from(10_000), 3600)),
+            (37, ClaimPendingWithdrawal(56, U256::from(7))),
+            (38, CancelPendingWithdrawal(1, U256::max_value())),
+            (39, SetSwapBounds(56, U256::from(100), U256::from(1_000_000u64))),
+            (40, GetSwapBounds(56)),
+            (41, SetChainAddressFormat(56, AddressFormat::Evm)),
+            (42, SetChainDecimals(56, 18)),
+            (43, SetFeeFloor(10_000_000)),
+            (44, WithdrawFees(u64::MAX)),
+            (
+                45,
+                SetFeeSplit(FeeSplitPayload {
+                    treasury_weight: 5_000,
+                    beneficiaries: vec![
+                        FeeBeneficiary {
+                            account: key(1),
+                            weight: 3_000,
+                        },
+                        FeeBeneficiary {
+                            account: key(2),
+                            weight: 2_000,
+                        },
+                    ],
+                }),
+            ),
+            (46, DistributeFees()),
+            (47, SetRelayerRefund(5_000)),
+            (48, ReviewTrippedSwap(56, false)),
+        ]
+    }
+
+    #[test]
+    fn serialize_deserialize_all_instructions() {
+        for (opcode, instruction) in all_instructions() {
+            let data = instruction.clone().serialize().unwrap();
+            assert_eq!(data[0], opcode);
+            assert_eq!(BridgeInstruction::deserialize(&data).unwrap(), instruction);
+        }
+    }
+
+    #[test]
+    fn opcodes_are_unique() {
+        let mut opcodes: Vec<u8> = all_instructions().iter().map(|(o, _)| *o).collect();
+        opcodes.sort();
+        opcodes.dedup();
+        assert_eq!(opcodes.len(), all_instructions().len());
+    }
+
+    #[test]
+    fn serialize_swap_layout() {
+        let data = Swap(SwapPayload {
+            amount: U256::from(0x0102u64),
+            toChainID: 0x0a0b0c0d,
+            recipient: [0xee; 32],
+            idempotency_key: 0x0102,
+            deadline: -2,
+        })
+        .serialize()
+        .unwrap();
+
+        let mut expected = vec![1u8];
+        expected.extend_from_slice(&[0u8; 30]);
+        expected.extend_from_slice(&[0x01, 0x02]);
+        expected.extend_from_slice(&[0x0d, 0x0c, 0x0b, 0x0a]);
+        expected.extend_from_slice(&[0xee; 32]);
+        expected.extend_from_slice(&[0x02, 0x01, 0, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn serialize_swap_back_layout() {
+        let data = SwapBack(SwapBackPayload {
+            to: key(9),
+            amount: U256::from(3),
+            nonce: U256::from(4),
+            fromChainID: 1,
+        })
+        .serialize()
+        .unwrap();
+
+        let mut expected = vec![2u8];
+        expected.extend_from_slice(&[9u8; 32]);
+        expected.extend_from_slice(&[0u8; 31]);
+        expected.push(3);
+        expected.extend_from_slice(&[0u8; 31]);
+        expected.push(4);
+        expected.extend_from_slice(&[1, 0, 0, 0]);
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn deserialize_rejects_malformed_input() {
+        // Empty and unknown opcodes
+        assert!(BridgeInstruction::deserialize(&[]).is_err());
+        let unknown_opcode = all_instructions().len() as u8;
+        assert!(BridgeInstruction::deserialize(&[unknown_opcode]).is_err());
+        assert!(BridgeInstruction::deserialize(&[255]).is_err());
+
+        for (_, instruction) in all_instructions() {
+            let data = instruction.serialize().unwrap();
+
+            // Truncated payloads
+            for len in 1..data.len() {
+                assert!(BridgeInstruction::deserialize(&data[..len]).is_err());
+            }
+
+            // Trailing bytes
+            let mut extended = data.clone();
+            extended.push(0);
+            assert!(BridgeInstruction::deserialize(&extended).is_err());
+        }
+
+        // Booleans other than 0 and 1
+        let mut data = UpdateBridgingStaus(true).serialize().unwrap();
+        data[1] = 2;
+        assert!(BridgeInstruction::deserialize(&data).is_err());
+
+        // Unknown directions
+        let mut data = ClearChainFee(1, SwapDirection::Inbound).serialize().unwrap();
+        data[5] = 2;
+        assert!(BridgeInstruction::deserialize(&data).is_err());
+
+        // Unknown config changes
+        let mut data = ExecuteConfigChange(ConfigChange::System).serialize().unwrap();
+        data[1] = 4;
+        assert!(BridgeInstruction::deserialize(&data).is_err());
+    }
+
+    #[test]
+    fn deserialize_serialize_random_payloads() {
+        // Whatever decodes successfully must encode back to the exact same bytes
+        for (_, instruction) in all_instructions() {
+            let len = instruction.serialize().unwrap().len();
+            for _ in 0..1000 {
+                let mut data: Vec<u8> = (0..len).map(|_| rand::random::<u8>()).collect();
+                data[0] = rand::random::<u8>() % all_instructions().len() as u8;
+                if let Ok(decoded) = BridgeInstruction::deserialize(&data) {
+                    assert_eq!(decoded.serialize().unwrap(), data);
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,9 @@
 #[macro_use]
-extern crate zerocopy;
-#[macro_use]
 extern crate solana_program;
 
+pub mod amount;
 pub mod entrypoint;
+pub mod error;
 pub mod error_program;
 pub mod instruction;
 pub mod processor;
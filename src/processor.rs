@@ -1,39 +1,34 @@
 //! Program instruction processing logic
 #![cfg(feature = "program")]
+#![allow(clippy::too_many_arguments)]
 
-use std::{borrow::Borrow, cell::RefCell, io::Write, mem::size_of, slice::Iter};
+use std::mem::size_of;
 
-use byteorder::ByteOrder;
-use num_traits::AsPrimitive;
 use primitive_types::U256;
-use sha3::Digest;
 use solana_program::program::invoke_signed;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     clock::Clock,
     entrypoint::ProgramResult,
-    hash::Hasher,
-    info,
-    instruction::Instruction,
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction::{create_account, SystemInstruction},
+    system_instruction::{allocate, assign, create_account, transfer},
     sysvar::Sysvar,
 };
-use spl_token::{state::Mint};
 
 use crate::{
+    amount::{Amount, DecimalMapping},
     error::Error,
     instruction::{
-        BridgeInstruction, BridgeInstruction::*, TransferOutPayload, VAAData, VerifySigPayload,
-        CHAIN_ID_SOLANA, MAX_LEN_GUARDIAN_KEYS, MAX_VAA_SIZE,
+        BridgeInstruction, BridgeInstruction::*, FeeSplitPayload, InitializePayload,
+        MultisigPayload, SwapBackPayload, SwapPayload, MAX_FEE_BENEFICIARIES,
+        MAX_MULTISIG_SIGNERS, MAX_PROPOSAL_ACCOUNTS, MAX_PROPOSAL_DATA,
     },
     state::*,
-    vaa::{BodyTransfer, BodyUpdateGuardianSet, VAABody, VAA},
 };
 use solana_program::program_pack::Pack;
-use solana_program::fee_calculator::FeeCalculator;
 
 /// Instruction processing logic
 impl Bridge {
@@ -41,238 +36,325 @@ impl Bridge {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = BridgeInstruction::deserialize(input)?;
         match instruction {
-            Initialize() => {
+            Initialize(payload) => {
                 msg!("Instruction: Initialize");
-                Self::process_initialize(
+                Self::process_initialize(program_id, accounts, &payload)
+            }
+            Swap(payload) => {
+                msg!("Instruction: Swap");
+                Self::process_swap(program_id, accounts, &payload)
+            }
+            SwapBack(payload) => {
+                msg!("Instruction: SwapBack");
+                Self::process_swap_back(program_id, accounts, &payload)
+            }
+            SetBridgeFee(fee) => {
+                msg!("Instruction: SetBridgeFee");
+                Self::process_set_bridge_fee(program_id, accounts, &fee)
+            }
+            GetBridgeFee() => {
+                msg!("Instruction: GetBridgeFee");
+                Self::process_get_bridge_fee(program_id, accounts)
+            }
+            FeeCalculation(chain_id, amount) => {
+                msg!("Instruction: FeeCalculation");
+                Self::process_fee_calculation(program_id, accounts, chain_id, amount)
+            }
+            SetChainFee(chain_id, direction, fee) => {
+                msg!("Instruction: SetChainFee");
+                Self::process_set_chain_fee(program_id, accounts, chain_id, direction, &fee)
+            }
+            ClearChainFee(chain_id, direction) => {
+                msg!("Instruction: ClearChainFee");
+                Self::process_set_chain_fee(
+                    program_id,
+                    accounts,
+                    chain_id,
+                    direction,
+                    &ChainFee::default(),
+                )
+            }
+            SetDefaultProcessedFee(fee) => {
+                msg!("Instruction: SetDefaultProcessedFee");
+                Self::process_set_default_processed_fee(program_id, accounts, fee)
+            }
+            UpdateCrogeProgram(croge_program) => {
+                msg!("Instruction: UpdateCrogeProgram");
+                let pending = PendingChange {
+                    new_key: croge_program,
+                    ..PendingChange::default()
+                };
+                Self::process_queue_config_change(
+                    program_id,
+                    accounts,
+                    ConfigChange::CrogeProgram,
+                    pending,
+                )
+            }
+            SetBridgeFeesAddress(address) => {
+                msg!("Instruction: SetBridgeFeesAddress");
+                let pending = PendingChange {
+                    new_key: address,
+                    ..PendingChange::default()
+                };
+                Self::process_queue_config_change(
+                    program_id,
+                    accounts,
+                    ConfigChange::BridgeFeesAddress,
+                    pending,
+                )
+            }
+            SetSystem(system) => {
+                msg!("Instruction: SetSystem");
+                let pending = PendingChange {
+                    new_key: system,
+                    ..PendingChange::default()
+                };
+                Self::process_queue_config_change(
+                    program_id,
+                    accounts,
+                    ConfigChange::System,
+                    pending,
+                )
+            }
+            SetTimelockDelay(delay) => {
+                msg!("Instruction: SetTimelockDelay");
+                let pending = PendingChange {
+                    new_delay: delay,
+                    ..PendingChange::default()
+                };
+                Self::process_queue_config_change(
+                    program_id,
+                    accounts,
+                    ConfigChange::TimelockDelay,
+                    pending,
+                )
+            }
+            ExecuteConfigChange(change) => {
+                msg!("Instruction: ExecuteConfigChange");
+                Self::process_execute_config_change(program_id, accounts, change)
+            }
+            CancelConfigChange(change) => {
+                msg!("Instruction: CancelConfigChange");
+                Self::process_cancel_config_change(program_id, accounts, change)
+            }
+            UpdateBridgingStaus(paused) => {
+                msg!("Instruction: UpdateBridgingStaus");
+                Self::process_update_bridging_status(program_id, accounts, paused)
+            }
+            SetMultisig(payload) => {
+                msg!("Instruction: SetMultisig");
+                Self::process_set_multisig(program_id, accounts, &payload)
+            }
+            ProposeAdminAction(action, action_accounts) => {
+                msg!("Instruction: ProposeAdminAction");
+                Self::process_propose_admin_action(program_id, accounts, &action, &action_accounts)
+            }
+            ApproveProposal(index) => {
+                msg!("Instruction: ApproveProposal");
+                Self::process_approve_proposal(program_id, accounts, index)
+            }
+            ExecuteProposal(index) => {
+                msg!("Instruction: ExecuteProposal");
+                Self::process_execute_proposal(program_id, accounts, index)
+            }
+            ChangeGovernor(governor) => {
+                msg!("Instruction: ChangeGovernor");
+                Self::process_propose_role(program_id, accounts, Role::Governor, &governor)
+            }
+            ChangeOwner(owner) => {
+                msg!("Instruction: ChangeOwner");
+                Self::process_propose_role(program_id, accounts, Role::Owner, &owner)
+            }
+            AcceptGovernor() => {
+                msg!("Instruction: AcceptGovernor");
+                Self::process_accept_role(program_id, accounts, Role::Governor)
+            }
+            AcceptOwner() => {
+                msg!("Instruction: AcceptOwner");
+                Self::process_accept_role(program_id, accounts, Role::Owner)
+            }
+            CancelGovernorChange() => {
+                msg!("Instruction: CancelGovernorChange");
+                Self::process_cancel_role_change(program_id, accounts, Role::Governor)
+            }
+            CancelOwnerChange() => {
+                msg!("Instruction: CancelOwnerChange");
+                Self::process_cancel_role_change(program_id, accounts, Role::Owner)
+            }
+            SetPauser(pauser) => {
+                msg!("Instruction: SetPauser");
+                Self::process_set_pauser(program_id, accounts, &pauser)
+            }
+            RevokePauser() => {
+                msg!("Instruction: RevokePauser");
+                Self::process_set_pauser(program_id, accounts, &Pubkey::default())
+            }
+            SetRateLimit(chain_id, direction, max_amount, window) => {
+                msg!("Instruction: SetRateLimit");
+                Self::process_set_rate_limit(
+                    program_id,
+                    accounts,
+                    chain_id,
+                    direction,
+                    max_amount,
+                    window,
+                )
+            }
+            SetCircuitBreaker(chain_id, max_amount, max_hourly_amount) => {
+                msg!("Instruction: SetCircuitBreaker");
+                Self::process_set_circuit_breaker(
+                    program_id,
+                    accounts,
+                    chain_id,
+                    max_amount,
+                    max_hourly_amount,
+                )
+            }
+            SetWithdrawalDelay(threshold, delay) => {
+                msg!("Instruction: SetWithdrawalDelay");
+                Self::process_set_withdrawal_delay(program_id, accounts, threshold, delay)
+            }
+            ClaimPendingWithdrawal(from_chain_id, nonce) => {
+                msg!("Instruction: ClaimPendingWithdrawal");
+                Self::process_claim_pending_withdrawal(program_id, accounts, from_chain_id, nonce)
+            }
+            CancelPendingWithdrawal(from_chain_id, nonce) => {
+                msg!("Instruction: CancelPendingWithdrawal");
+                Self::process_cancel_pending_withdrawal(program_id, accounts, from_chain_id, nonce)
+            }
+            SetSwapBounds(chain_id, min_amount, max_amount) => {
+                msg!("Instruction: SetSwapBounds");
+                Self::process_set_swap_bounds(
                     program_id,
                     accounts,
+                    chain_id,
+                    min_amount,
+                    max_amount,
                 )
             }
-           
-            _ => panic!(""),
+            GetSwapBounds(chain_id) => {
+                msg!("Instruction: GetSwapBounds");
+                Self::process_get_swap_bounds(program_id, accounts, chain_id)
+            }
+            SetChainAddressFormat(chain_id, format) => {
+                msg!("Instruction: SetChainAddressFormat");
+                Self::process_set_chain_address_format(program_id, accounts, chain_id, format)
+            }
+            SetChainDecimals(chain_id, decimals) => {
+                msg!("Instruction: SetChainDecimals");
+                Self::process_set_chain_decimals(program_id, accounts, chain_id, decimals)
+            }
+            SetFeeFloor(floor) => {
+                msg!("Instruction: SetFeeFloor");
+                Self::process_set_fee_floor(program_id, accounts, floor)
+            }
+            WithdrawFees(amount) => {
+                msg!("Instruction: WithdrawFees");
+                Self::process_withdraw_fees(program_id, accounts, amount)
+            }
+            SetFeeSplit(payload) => {
+                msg!("Instruction: SetFeeSplit");
+                Self::process_set_fee_split(program_id, accounts, &payload)
+            }
+            DistributeFees() => {
+                msg!("Instruction: DistributeFees");
+                Self::process_distribute_fees(program_id, accounts)
+            }
+            SetRelayerRefund(refund) => {
+                msg!("Instruction: SetRelayerRefund");
+                Self::process_set_relayer_refund(program_id, accounts, refund)
+            }
+            ReviewTrippedSwap(chain_id, approve) => {
+                msg!("Instruction: ReviewTrippedSwap");
+                Self::process_review_tripped_swap(program_id, accounts, chain_id, approve)
+            }
+            SetChainPaused(chain_id, direction, paused) => {
+                msg!("Instruction: SetChainPaused");
+                Self::process_set_chain_paused(program_id, accounts, chain_id, direction, paused)
+            }
+            SetChainEnabled(chain_id, enabled) => {
+                msg!("Instruction: SetChainEnabled");
+                Self::process_set_chain_enabled(program_id, accounts, chain_id, enabled)
+            }
+            SetProcessedFess(chain_id, fee) => {
+                msg!("Instruction: SetProcessedFees");
+                Self::process_set_processed_fees(program_id, accounts, chain_id, fee)
+            }
+            GetProcessedFees(chain_id) => {
+                msg!("Instruction: GetProcessedFees");
+                Self::process_get_processed_fees(program_id, accounts, chain_id)
+            }
+            ExcludeFromFees(wallet, excluded) => {
+                msg!("Instruction: ExcludeFromFees");
+                Self::process_exclude_from_fees(program_id, accounts, &wallet, excluded)
+            }
+            GetBridgeStatus(nonce, from_chain_id) => {
+                msg!("Instruction: GetBridgeStatus");
+                Self::process_get_bridge_status(program_id, accounts, nonce, from_chain_id)
+            }
         }
     }
 
-    /// Unpacks a token state from a bytes buffer while assuring that the state is initialized.
+    /// Creates the bridge config with all roles set
     pub fn process_initialize(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-    ) -> ProgramResult {
-        
-        Ok(())
-    }
-
-    /// Transfers a wrapped asset out
-    pub fn process_poke(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let proposal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-
-        let mut transfer_data = proposal_info.try_borrow_mut_data()?;
-        let mut proposal: &mut TransferOutProposal = Self::unpack(&mut transfer_data)?;
-        if proposal.vaa_time != 0 {
-            return Err(Error::VAAAlreadySubmitted.into());
-        }
-
-        // Increase poke counter
-        proposal.poke_counter += 1;
-
-        Ok(())
-    }
-
-    /// Processes signature verifications
-    pub fn process_verify_signatures(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        payload: &VerifySigPayload,
+        payload: &InitializePayload,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        next_account_info(account_info_iter)?; // Bridge program
         next_account_info(account_info_iter)?; // System program
-        let instruction_accounts = next_account_info(account_info_iter)?;
-        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let sig_info = next_account_info(account_info_iter)?;
-        let guardian_set_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let new_bridge_info = next_account_info(account_info_iter)?;
+        let program_data_info = next_account_info(account_info_iter)?;
         let payer_info = next_account_info(account_info_iter)?;
 
-        if *instruction_accounts.key != solana_program::sysvar::instructions::id() {
-            return Err(Error::InvalidSysvar.into());
-        }
-
-        // Verify bridge key because it is used as subsidizer
-        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
-        if *bridge_info.key != expected_bridge_key {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let guardian_data = guardian_set_info.try_borrow_data()?;
-        let guardian_set: &GuardianSet = Self::unpack_immutable(&guardian_data)?;
-
-        let sig_infos: Vec<SigInfo> = payload
-            .signers
-            .iter()
-            .enumerate()
-            .filter_map(|(i, p)| {
-                if *p == -1 {
-                    return None;
-                }
-
-                return Some(SigInfo {
-                    sig_index: *p as u8,
-                    signer_index: i as u8,
-                });
-            })
-            .collect();
-
-        let current_instruction = solana_program::sysvar::instructions::load_current_index(
-            &instruction_accounts.try_borrow_mut_data()?,
-        );
-        if current_instruction == 0 {
-            return Err(ProgramError::InvalidInstructionData);
-        }
-
-        // The previous ix must be a secp verification instruction
-        let secp_ix_index = (current_instruction - 1) as u8;
-        let secp_ix = solana_program::sysvar::instructions::load_instruction_at(
-            secp_ix_index as usize,
-            &instruction_accounts.try_borrow_mut_data()?,
-        )
-            .map_err(|_| ProgramError::InvalidAccountData)?;
-
-        // Check that the instruction is actually for the secp program
-        if secp_ix.program_id != solana_program::secp256k1_program::id() {
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        let secp_data_len = secp_ix.data.len();
-        if secp_data_len < 2 {
-            return Err(ProgramError::InvalidAccountData);
-        }
-
-        let sig_len = secp_ix.data[0];
-        let mut index = 1;
-
-        let mut secp_ixs: Vec<SecpInstructionPart> = Vec::with_capacity(sig_len as usize);
-        for i in 0..sig_len {
-            let sig_offset = byteorder::LE::read_u16(&secp_ix.data[index..index + 2]) as usize;
-            index += 2;
-            let sig_ix = secp_ix.data[index];
-            index += 1;
-            let address_offset = byteorder::LE::read_u16(&secp_ix.data[index..index + 2]) as usize;
-            index += 2;
-            let address_ix = secp_ix.data[index];
-            index += 1;
-            let msg_offset = byteorder::LE::read_u16(&secp_ix.data[index..index + 2]);
-            index += 2;
-            let msg_size = byteorder::LE::read_u16(&secp_ix.data[index..index + 2]);
-            index += 2;
-            let msg_ix = secp_ix.data[index];
-            index += 1;
-
-            if address_ix != secp_ix_index || msg_ix != secp_ix_index || sig_ix != secp_ix_index {
-                return Err(ProgramError::InvalidArgument);
-            }
-
-            let address: &[u8] = &secp_ix.data[address_offset..address_offset + 20];
-            let signature: &[u8] = &secp_ix.data[sig_offset..sig_offset + 65];
-
-            // Make sure that all messages are equal
-            if i > 0 {
-                if msg_offset != secp_ixs[0].msg_offset || msg_size != secp_ixs[0].msg_size {
-                    return Err(ProgramError::InvalidArgument);
-                }
-            }
-            secp_ixs.push(SecpInstructionPart {
-                address,
-                signature,
-                msg_offset,
-                msg_size,
-            });
-        }
-
-        if sig_infos.len() != secp_ixs.len() {
-            return Err(ProgramError::InvalidArgument);
-        }
-
-        // Check message
-        let message = &secp_ix.data[secp_ixs[0].msg_offset as usize
-            ..(secp_ixs[0].msg_offset + secp_ixs[0].msg_size) as usize];
-
-        let mut h = sha3::Keccak256::default();
-        if let Err(_) = h.write(message) {
-            return Err(ProgramError::InvalidArgument);
-        };
-        let msg_hash: [u8; 32] = h.finalize().into();
-        if msg_hash != payload.hash {
-            return Err(ProgramError::InvalidArgument);
-        }
+        // Only the deployer may initialize, otherwise anyone could front-run the setup
+        Self::check_upgrade_authority(program_id, program_data_info, payer_info)?;
 
-        // Prepare message/payload-specific sig_info account
-        if sig_info.data_is_empty() {
-            let bridge_key = Bridge::derive_bridge_id(program_id)?;
-            let sig_seeds =
-                Bridge::derive_signature_seeds(&bridge_key, &msg_hash, guardian_set.index);
-            Bridge::check_and_create_account::<SignatureState>(
-                program_id,
-                accounts,
-                sig_info.key,
-                payer_info,
-                program_id,
-                &sig_seeds,
-                Some(bridge_info),
-            )?;
-        } else if payload.initial_creation {
+        if !new_bridge_info.data_is_empty() {
             return Err(Error::AlreadyExists.into());
         }
+        payload.bridge_fee.validate()?;
 
-        let mut sig_state_data = sig_info.try_borrow_mut_data()?;
-        let mut sig_state: &mut SignatureState = Self::unpack_unchecked(&mut sig_state_data)?;
+        // Create bridge account
+        let bridge_seed = Bridge::derive_bridge_seeds();
+        Bridge::check_and_create_account::<Bridge>(
+            program_id,
+            accounts,
+            new_bridge_info.key,
+            payer_info,
+            program_id,
+            &bridge_seed,
+            None,
+        )?;
 
-        if sig_state.is_initialized {
-            if sig_state.guardian_set_index != guardian_set.index {
-                return Err(Error::GuardianSetMismatch.into());
-            }
-            if sig_state.hash != payload.hash {
-                return Err(ProgramError::InvalidArgument);
-            }
-        } else {
-            sig_state.is_initialized = true;
-            sig_state.guardian_set_index = guardian_set.index;
-            sig_state.hash = payload.hash;
+        let mut new_account_data = new_bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack_unchecked(&mut new_account_data)?;
+        if bridge.is_initialized {
+            return Err(Error::AlreadyExists.into());
         }
 
-        // Write sigs of checked addresses into sig_state
-        for s in sig_infos {
-            if s.signer_index > guardian_set.len_keys {
-                return Err(ProgramError::InvalidArgument);
-            }
-
-            if s.sig_index + 1 > sig_len {
-                return Err(ProgramError::InvalidArgument);
-            }
-
-            let key = guardian_set.keys[s.signer_index as usize];
-            // Check key in ix
-            if key != secp_ixs[s.sig_index as usize].address {
-                return Err(ProgramError::InvalidArgument);
-            }
-
-            // Overwritten content should be zeros except double signs by the signer or harmless replays
-            sig_state.signatures[s.signer_index as usize]
-                .copy_from_slice(secp_ixs[s.sig_index as usize].signature);
-        }
+        // Initialize bridge params
+        bridge.is_initialized = true;
+        bridge.config.croge_program = payload.croge_program;
+        bridge.config.system = payload.system;
+        bridge.config.governor = payload.governor;
+        bridge.config.bridgeFeesAddress = payload.bridgeFeesAddress;
+        bridge.config.owner = payload.owner;
+        bridge.config.bridge_fee = payload.bridge_fee;
+        bridge.config.processed_fee = payload.processed_fee;
+        bridge.config.timelock_delay = PendingChange::DEFAULT_DELAY;
 
         Ok(())
     }
 
-    /// Transfers a wrapped asset out
-    pub fn process_transfer_out(
+    /// Locks Croge tokens in custody and records the swap for the relayer
+    pub fn process_swap(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        t: &TransferOutPayload,
+        payload: &SwapPayload,
     ) -> ProgramResult {
-        msg!("wrapped transfer out");
         let account_info_iter = &mut accounts.iter();
-        next_account_info(account_info_iter)?; // Bridge program
         next_account_info(account_info_iter)?; // System program
         next_account_info(account_info_iter)?; // Token program
         next_account_info(account_info_iter)?; // Rent sysvar
@@ -280,153 +362,114 @@ impl Bridge {
         let instructions_info = next_account_info(account_info_iter)?;
         let sender_account_info = next_account_info(account_info_iter)?;
         let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let transfer_info = next_account_info(account_info_iter)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let swap_info = next_account_info(account_info_iter)?;
+        let idempotency_info = next_account_info(account_info_iter)?;
         let mint_info = next_account_info(account_info_iter)?;
+        let custody_info = next_account_info(account_info_iter)?;
+        let exemption_info = next_account_info(account_info_iter)?;
+        let fee_info = next_account_info(account_info_iter)?;
         let payer_info = next_account_info(account_info_iter)?;
 
         let sender = Bridge::token_account_deserialize(sender_account_info)?;
         let bridge_data = bridge_info.try_borrow_data()?;
         let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
-        let mint = Bridge::mint_deserialize(mint_info)?;
         let clock = Clock::from_account_info(clock_info)?;
 
         if *instructions_info.key != solana_program::sysvar::instructions::id() {
             return Err(Error::InvalidSysvar.into());
         }
 
-        // Fee handling
-        let fee = Self::transfer_fee();
-        Self::check_fees(instructions_info, bridge_info, fee)?;
-
-        // Does the token belong to the mint
-        if sender.mint != *mint_info.key {
-            return Err(Error::TokenMintMismatch.into());
+        // Verify bridge key because it owns the custody account
+        let bridge_authority = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != bridge_authority {
+            return Err(ProgramError::InvalidAccountData);
         }
 
-        // Check that the mint is actually a wrapped asset belonging to *this* bridge instance
-        let expected_mint_address = Bridge::derive_wrapped_asset_id(
-            program_id,
-            bridge_info.key,
-            t.asset.chain,
-            t.asset.decimals,
-            t.asset.address,
-        )?;
-        if expected_mint_address != *mint_info.key {
-            return Err(Error::InvalidDerivedAccount.into());
+        if bridge._isBridgingPaused {
+            return Err(Error::BridgingPaused.into());
         }
 
-        // Create transfer account
-        let transfer_seed = Bridge::derive_transfer_id_seeds(
-            bridge_info.key,
-            t.asset.chain,
-            t.asset.address,
-            t.chain_id,
-            t.target,
-            sender_account_info.key.to_bytes(),
-            t.nonce,
-        );
-        Bridge::check_and_create_account::<TransferOutProposal>(
-            program_id,
-            accounts,
-            transfer_info.key,
-            payer_info,
-            program_id,
-            &transfer_seed,
-            None,
-        )?;
-
-        // Load transfer account
-        let mut transfer_data = transfer_info.try_borrow_mut_data()?;
-        let mut transfer: &mut TransferOutProposal = Self::unpack_unchecked(&mut transfer_data)?;
-
-        // Burn tokens
-        Bridge::wrapped_burn(
-            program_id,
-            accounts,
-            &bridge.config.token_program,
-            sender_account_info.key,
-            mint_info.key,
-            t.amount,
-        )?;
-
-        // Initialize transfer
-        transfer.is_initialized = true;
-        transfer.nonce = t.nonce;
-        transfer.source_address = sender_account_info.key.to_bytes();
-        transfer.foreign_address = t.target;
-        transfer.amount = t.amount;
-        transfer.to_chain_id = t.chain_id;
-        transfer.lockup_time = clock.unix_timestamp as u32;
-
-        // Make sure decimals are correct
-        transfer.asset = AssetMeta {
-            chain: t.asset.chain, // Chain and address cannot be spoofed because the account is derived from it
-            address: t.asset.address,
-            decimals: mint.decimals, // We use the info from mint because it can be spoofed
-        };
+        Self::check_chain_state(program_id, bridge_info, chain_info, payload.toChainID)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        if !chain.is_enabled {
+            return Err(Error::ChainDisabled.into());
+        }
+        if chain.is_outbound_paused {
+            return Err(Error::OutboundPaused.into());
+        }
 
-        Ok(())
-    }
+        if payload.deadline != 0 && clock.unix_timestamp > payload.deadline {
+            return Err(Error::SwapExpired.into());
+        }
 
-    /// Transfers a native token to a foreign chain
-    pub fn process_transfer_native_out(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        t: &TransferOutPayload,
-    ) -> ProgramResult {
-        msg!("native transfer out");
-        let account_info_iter = &mut accounts.iter();
-        next_account_info(account_info_iter)?; // Bridge program
-        next_account_info(account_info_iter)?; // System program
-        next_account_info(account_info_iter)?; // Token program
-        next_account_info(account_info_iter)?; // Rent sysvar
-        let clock_info = next_account_info(account_info_iter)?;
-        let instructions_info = next_account_info(account_info_iter)?;
-        let sender_account_info = next_account_info(account_info_iter)?;
-        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let transfer_info = next_account_info(account_info_iter)?;
-        let mint_info = next_account_info(account_info_iter)?;
-        let payer_info = next_account_info(account_info_iter)?;
-        let custody_info = next_account_info(account_info_iter)?;
+        if payload.amount.is_zero() {
+            return Err(ProgramError::InvalidArgument);
+        }
+        chain.check_amount(payload.amount)?;
+        chain.address_format.validate(&payload.recipient)?;
+        chain
+            .outbound_limit
+            .consume(clock.unix_timestamp, payload.amount)?;
 
-        let sender = Bridge::token_account_deserialize(sender_account_info)?;
-        let mint = Bridge::mint_deserialize(mint_info)?;
-        let bridge_data = bridge_info.try_borrow_data()?;
-        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
-        let clock = Clock::from_account_info(clock_info)?;
+        // Fee handling
+        let processed_fee = chain.outbound_fee.resolve_processed_fee(&bridge.config);
+        if processed_fee > 0 {
+            Self::check_fees(instructions_info, bridge_info, processed_fee)?;
+        }
 
-        let fee = Self::transfer_fee();
-        Self::check_fees(instructions_info, bridge_info, fee)?;
+        // Only Croge can be swapped
+        if *mint_info.key != bridge.config.croge_program {
+            return Err(Error::TokenMintMismatch.into());
+        }
 
         // Does the token belong to the mint
         if sender.mint != *mint_info.key {
             return Err(Error::TokenMintMismatch.into());
         }
 
-        // Create transfer account
-        let transfer_seed = Bridge::derive_transfer_id_seeds(
-            bridge_info.key,
-            t.asset.chain,
-            t.asset.address,
-            t.chain_id,
-            t.target,
-            sender_account_info.key.to_bytes(),
-            t.nonce,
-        );
-        Bridge::check_and_create_account::<TransferOutProposal>(
+        // The owner of the tokens has to sign the swap
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if sender.owner != *payer_info.key {
+            return Err(Error::WrongTokenAccountOwner.into());
+        }
+
+        // Assign the next nonce of the chain to this swap
+        let nonce = chain
+            .nonce
+            .checked_add(U256::one())
+            .ok_or(ProgramError::InvalidArgument)?;
+        chain.nonce = nonce;
+
+        // Claim the idempotency key, this fails if the sender used it before
+        if payload.idempotency_key != 0 {
+            Self::create_swap_idempotency(
+                program_id,
+                accounts,
+                bridge_info,
+                idempotency_info,
+                payer_info,
+                payload.idempotency_key,
+                payload.toChainID,
+                nonce,
+            )?;
+        }
+
+        // Create swap record
+        let swap_seeds = Bridge::derive_swap_record_seeds(bridge_info.key, payload.toChainID, nonce);
+        Bridge::check_and_create_account::<SwapRecord>(
             program_id,
             accounts,
-            transfer_info.key,
+            swap_info.key,
             payer_info,
             program_id,
-            &transfer_seed,
+            &swap_seeds,
             None,
         )?;
 
-        // Load transfer account
-        let mut transfer_data = transfer_info.try_borrow_mut_data()?;
-        let mut transfer: &mut TransferOutProposal = Self::unpack_unchecked(&mut transfer_data)?;
-
         // Check that custody account was derived correctly
         let expected_custody_id =
             Bridge::derive_custody_id(program_id, bridge_info.key, mint_info.key)?;
@@ -439,7 +482,7 @@ impl Bridge {
             Bridge::create_custody_account(
                 program_id,
                 accounts,
-                &bridge.config.token_program,
+                &spl_token::id(),
                 bridge_info.key,
                 custody_info.key,
                 mint_info.key,
@@ -448,8 +491,6 @@ impl Bridge {
             )?;
         }
 
-        let bridge_authority = Self::derive_bridge_id(program_id)?;
-
         // Check that the custody token account is owned by the derived key
         let custody = Self::token_account_deserialize(custody_info)?;
         if custody.owner != bridge_authority {
@@ -461,795 +502,4035 @@ impl Bridge {
             return Err(Error::WrongTokenAccountOwner.into());
         }
 
-        msg!("transferring");
+        // The bridge fee is waived if the sender is excluded from fees
+        let fee = if Self::is_excluded_from_fees(
+            program_id,
+            bridge_info,
+            exemption_info,
+            payer_info.key,
+        )? {
+            U256::zero()
+        } else {
+            chain
+                .outbound_fee
+                .resolve_bridge_fee(&bridge.config)
+                .calculate(payload.amount)?
+        };
+        let amount = payload
+            .amount
+            .checked_sub(fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        // Less than a unit of the destination chain can not be released there, like the dust of
+        // inbound swaps it is kept as a fee
+        let (foreign_amount, remainder) = chain.decimals.to_foreign(Amount(amount))?;
+        let amount = amount - remainder.0;
+        let fee = fee + remainder.0;
+        if amount.is_zero() {
+            return Err(Error::InsufficientFees.into());
+        }
+
+        if !fee.is_zero() {
+            Self::check_and_create_fee_custody(
+                program_id,
+                accounts,
+                bridge_info,
+                bridge,
+                fee_info,
+                payer_info,
+            )?;
+            Bridge::token_transfer_caller(
+                program_id,
+                accounts,
+                &spl_token::id(),
+                sender_account_info.key,
+                fee_info.key,
+                payer_info.key,
+                Amount(fee),
+            )?;
+        }
+
         // Transfer tokens to custody - This also checks that custody mint = mint
         Bridge::token_transfer_caller(
             program_id,
             accounts,
-            &bridge.config.token_program,
+            &spl_token::id(),
             sender_account_info.key,
             custody_info.key,
-            &bridge_authority,
-            t.amount,
+            payer_info.key,
+            Amount(amount),
         )?;
 
-        // Initialize proposal
-        transfer.is_initialized = true;
-        transfer.amount = t.amount;
-        transfer.to_chain_id = t.chain_id;
-        transfer.source_address = sender_account_info.key.to_bytes();
-        transfer.foreign_address = t.target;
-        transfer.nonce = t.nonce;
-        transfer.lockup_time = clock.unix_timestamp as u32;
-
-        // Don't use the user-given data as we don't check mint = AssetMeta.address
-        transfer.asset = AssetMeta {
-            chain: CHAIN_ID_SOLANA,
-            address: mint_info.key.to_bytes(),
-            decimals: mint.decimals,
-        };
+        // Initialize swap record
+        let mut swap_data = swap_info.try_borrow_mut_data()?;
+        let swap: &mut SwapRecord = Self::unpack_unchecked(&mut swap_data)?;
+        swap.is_initialized = true;
+        swap.to_chain_id = payload.toChainID;
+        swap.nonce = nonce;
+        swap.sender = *payer_info.key;
+        swap.recipient = payload.recipient;
+        swap.amount = foreign_amount.0;
+        swap.lockup_time = clock.unix_timestamp as u32;
+
+        msg!("swap {} to chain {}: {}", nonce, payload.toChainID, foreign_amount.0);
 
         Ok(())
     }
 
-    /// Verify that a certain fee was sent to the bridge in the preceding instruction
-    pub fn check_fees(instructions_info: &AccountInfo, bridge_info: &AccountInfo, fee: u64) -> Result<(), ProgramError> {
-        let current_instruction = solana_program::sysvar::instructions::load_current_index(
-            &instructions_info.try_borrow_mut_data()?,
-        );
-        if current_instruction == 0 {
-            return Err(ProgramError::InvalidInstructionData);
+    /// Pays out an inbound swap from custody, as reported by the system authority
+    pub fn process_swap_back(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        payload: &SwapBackPayload,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        next_account_info(account_info_iter)?; // Token program
+        next_account_info(account_info_iter)?; // Rent sysvar
+        let clock_info = next_account_info(account_info_iter)?;
+        let instructions_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+        let withdrawal_info = next_account_info(account_info_iter)?;
+        let exemption_info = next_account_info(account_info_iter)?;
+        let custody_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let fee_info = next_account_info(account_info_iter)?;
+        let system_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        let clock = Clock::from_account_info(clock_info)?;
+
+        if *instructions_info.key != solana_program::sysvar::instructions::id() {
+            return Err(Error::InvalidSysvar.into());
         }
 
-        // The previous ix must be a transfer instruction
-        let transfer_ix_index = (current_instruction - 1) as u8;
-        let transfer_ix = solana_program::sysvar::instructions::load_instruction_at(
-            transfer_ix_index as usize,
-            &instructions_info.try_borrow_mut_data()?,
-        )
-            .map_err(|_| ProgramError::InvalidAccountData)?;
+        // Verify bridge key because it owns the custody account
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
 
-        // Check that the instruction is actually for the system program
-        if transfer_ix.program_id != solana_program::system_program::id() {
-            return Err(ProgramError::InvalidArgument);
+        // Only the system authority may report inbound swaps
+        if !system_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *system_info.key != bridge.config.system {
+            return Err(Error::InvalidSystem.into());
         }
 
-        if transfer_ix.accounts.len() != 2 {
-            return Err(ProgramError::InvalidInstructionData);
+        if bridge._isBridgingPaused {
+            return Err(Error::BridgingPaused.into());
         }
 
-        // Check that the fee was transferred to the bridge config.
-        // We only care that the fee was sent to the bridge, not by whom it was sent.
-        if transfer_ix.accounts[1].pubkey != *bridge_info.key {
+        Self::check_chain_state(program_id, bridge_info, chain_info, payload.fromChainID)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        if !chain.is_enabled {
+            return Err(Error::ChainDisabled.into());
+        }
+        if chain.is_inbound_paused {
+            return Err(Error::InboundPaused.into());
+        }
+
+        if payload.amount.is_zero() {
             return Err(ProgramError::InvalidArgument);
         }
+        // Scale to the decimals of the mint, amounts that do not fit into a token account can
+        // never be paid out
+        let (local_amount, dust) = chain.decimals.to_local(Amount(payload.amount))?;
+        local_amount.to_token_amount()?;
 
-        // The transfer instruction is serialized using bincode (little endian)
-        // uint32 ix_type = 2 (Transfer)
-        // uint64 lamports
-        // LEN: 4 + 8 = 12 bytes
-        if transfer_ix.data.len() != 12 {
-            return Err(ProgramError::InvalidAccountData);
+        // A relayer retry of a processed swap is rejected before it can count towards the breaker
+        let expected_receipt_id = Bridge::derive_swap_back_receipt_id(
+            program_id,
+            bridge_info.key,
+            payload.fromChainID,
+            payload.nonce,
+        )?;
+        if *receipt_info.key != expected_receipt_id {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+        if !receipt_info.data_is_empty() {
+            return Err(Error::SwapAlreadyProcessed.into());
         }
 
-        // Verify action
-        if transfer_ix.data[..4] != [2, 0, 0, 0] {
-            return Err(ProgramError::InvalidInstructionData);
+        let processed_fee = chain.inbound_fee.resolve_processed_fee(&bridge.config);
+        if processed_fee > 0 {
+            Self::check_fees(instructions_info, bridge_info, processed_fee)?;
         }
 
-        // Parse amount
-        let mut fixed_data = [0u8; 8];
-        fixed_data.copy_from_slice(&transfer_ix.data[4..]);
-        let amount = u64::from_le_bytes(fixed_data);
+        // A suspicious swap pauses the chain and stays unprocessed until the governor resumes it,
+        // then it can be resubmitted. The breaker, the limit and the withdrawal threshold count
+        // amounts of the mint.
+        if chain
+            .inbound_breaker
+            .trips(clock.unix_timestamp, payload.nonce, local_amount.0)
+        {
+            chain.is_inbound_paused = true;
+            msg!(&format!(
+                "circuit breaker tripped: chain {}, nonce {}, amount {}, time {}",
+                payload.fromChainID,
+                payload.nonce,
+                local_amount.0,
+                clock.unix_timestamp
+            ));
+            return Ok(());
+        }
+        chain
+            .inbound_limit
+            .consume(clock.unix_timestamp, local_amount.0)?;
 
-        // Verify fee amount
-        if amount < fee {
-            return Err(Error::InsufficientFees.into());
+        // Mark the swap as processed
+        Self::create_swap_back_receipt(
+            program_id,
+            accounts,
+            bridge_info,
+            receipt_info,
+            system_info,
+            &clock,
+            payload.fromChainID,
+            payload.nonce,
+        )?;
+
+        // Refund the transaction fee of the relayer if the bridge can afford it
+        Self::refund_relayer(bridge_info, bridge, system_info)?;
+
+        let expected_custody_id =
+            Bridge::derive_custody_id(program_id, bridge_info.key, &bridge.config.croge_program)?;
+        if expected_custody_id != *custody_info.key {
+            return Err(Error::InvalidDerivedAccount.into());
         }
 
-        Ok(())
-    }
+        if *destination_info.key != payload.to {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let destination = Self::token_account_deserialize(destination_info)?;
+        if destination.mint != bridge.config.croge_program {
+            return Err(Error::TokenMintMismatch.into());
+        }
 
-    pub fn transfer_sol(
-        payer_account: &AccountInfo,
-        recipient_account: &AccountInfo,
-        amount: u64,
-    ) -> ProgramResult {
-        let mut payer_balance = payer_account.try_borrow_mut_lamports()?;
-        **payer_balance = payer_balance.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
-        let mut recipient_balance = recipient_account.try_borrow_mut_lamports()?;
-        **recipient_balance = recipient_balance.checked_add(amount).ok_or(ProgramError::InvalidArgument)?;
+        // Fees are waived if the owner of the recipient account is excluded from them
+        let fee = if Self::is_excluded_from_fees(
+            program_id,
+            bridge_info,
+            exemption_info,
+            &destination.owner,
+        )? {
+            U256::zero()
+        } else {
+            chain
+                .inbound_fee
+                .resolve_bridge_fee(&bridge.config)
+                .calculate(local_amount.0)?
+        };
+        let amount = local_amount
+            .0
+            .checked_sub(fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        // Dust that adds up to a whole unit of the mint is paid out as fees
+        let dust = chain
+            .inbound_dust
+            .checked_add(dust.0)
+            .ok_or(Error::AmountOverflow)?;
+        let (dust_fee, dust) = chain.decimals.to_local(Amount(dust))?;
+        chain.inbound_dust = dust.0;
+        let fee = fee.checked_add(dust_fee.0).ok_or(Error::AmountOverflow)?;
+
+        // Fees accrue in the fee custody account until they are distributed
+        if !fee.is_zero() {
+            Self::check_and_create_fee_custody(
+                program_id,
+                accounts,
+                bridge_info,
+                bridge,
+                fee_info,
+                system_info,
+            )?;
+        }
+
+        // Large swaps are held back and have to be claimed after the withdrawal delay
+        let threshold = bridge.config.large_withdrawal_threshold;
+        if !threshold.is_zero() && local_amount.0 > threshold {
+            let withdrawal_seeds = Bridge::derive_pending_withdrawal_seeds(
+                bridge_info.key,
+                payload.fromChainID,
+                payload.nonce,
+            );
+            Bridge::check_and_create_or_reuse_account::<PendingWithdrawal>(
+                program_id,
+                accounts,
+                withdrawal_info,
+                system_info,
+                &withdrawal_seeds,
+                Error::AlreadyExists,
+            )?;
+
+            let mut withdrawal_data = withdrawal_info.try_borrow_mut_data()?;
+            let withdrawal: &mut PendingWithdrawal = Self::unpack_unchecked(&mut withdrawal_data)?;
+            withdrawal.is_initialized = true;
+            withdrawal.from_chain_id = payload.fromChainID;
+            withdrawal.nonce = payload.nonce;
+            withdrawal.to = payload.to;
+            withdrawal.amount = amount;
+            withdrawal.fee = fee;
+            withdrawal.release_time =
+                clock.unix_timestamp + bridge.config.withdrawal_delay as i64;
+            withdrawal.payer = *system_info.key;
+
+            msg!(&format!(
+                "swap back {} from chain {} held until {}: {}",
+                payload.nonce,
+                payload.fromChainID,
+                withdrawal.release_time,
+                local_amount.0
+            ));
+
+            return Ok(());
+        }
+
+        Self::transfer_swap_back(
+            program_id,
+            accounts,
+            bridge_info,
+            bridge,
+            custody_info,
+            destination_info,
+            fee_info,
+            amount,
+            fee,
+        )?;
+
+        msg!("swap back {} from chain {}: {}", payload.nonce, payload.fromChainID, local_amount.0);
 
         Ok(())
     }
 
-    /// Processes a VAA
-    pub fn process_vaa(
+    /// Pays out a held back inbound swap after its delay
+    pub fn process_claim_pending_withdrawal(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        vaa_data: VAAData,
-        vaa: &VAA,
+        from_chain_id: u32,
+        nonce: U256,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-
-        // Load VAA processing default accounts
-        next_account_info(account_info_iter)?; // Bridge program
-        next_account_info(account_info_iter)?; // System program
-        next_account_info(account_info_iter)?; // Rent sysvar
+        next_account_info(account_info_iter)?; // Token program
         let clock_info = next_account_info(account_info_iter)?;
         let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let guardian_set_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let claim_info = next_account_info(account_info_iter)?;
-        let sig_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let withdrawal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let custody_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let fee_info = next_account_info(account_info_iter)?;
         let payer_info = next_account_info(account_info_iter)?;
 
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
         let clock = Clock::from_account_info(clock_info)?;
-        let mut guardian_data = guardian_set_info.try_borrow_mut_data()?;
-        let guardian_set: &mut GuardianSet = Bridge::unpack(&mut guardian_data)?;
 
-        // Verify bridge key because it is used as subsidizer and for key derivation
+        // Verify bridge key because it owns the custody account
         let expected_bridge_key = Self::derive_bridge_id(program_id)?;
         if *bridge_info.key != expected_bridge_key {
             return Err(ProgramError::InvalidAccountData);
         }
 
-        // Check that the guardian set is valid
-        let expected_guardian_set =
-            Bridge::derive_guardian_set_id(program_id, bridge_info.key, vaa.guardian_set_index)?;
-        if expected_guardian_set != *guardian_set_info.key {
-            return Err(Error::InvalidDerivedAccount.into());
+        // Held back swaps must not be claimed while the chain is under review
+        if bridge._isBridgingPaused {
+            return Err(Error::BridgingPaused.into());
         }
-
-        // Check that the guardian set is still active
-        if guardian_set.expiration_time != 0 && (guardian_set.expiration_time as i64) < clock.unix_timestamp {
-            return Err(Error::GuardianSetExpired.into());
+        Self::check_chain_state(program_id, bridge_info, chain_info, from_chain_id)?;
+        let chain_data = chain_info.try_borrow_data()?;
+        let chain: &ChainState = Self::unpack_immutable(&chain_data)?;
+        if chain.is_inbound_paused {
+            return Err(Error::InboundPaused.into());
         }
 
-        // Verify sig state
-        let mut sig_state_data = sig_info.try_borrow_mut_data()?;
-        let sig_state: &SignatureState = Self::unpack(&mut sig_state_data)?;
-
-        // Verify that signatures were made using the correct set
-        if sig_state.guardian_set_index != guardian_set.index {
-            return Err(Error::GuardianSetMismatch.into());
+        let withdrawal = Self::close_pending_withdrawal(
+            program_id,
+            bridge_info,
+            withdrawal_info,
+            payer_info,
+            from_chain_id,
+            nonce,
+        )?;
+        if clock.unix_timestamp < withdrawal.release_time {
+            return Err(Error::WithdrawalLocked.into());
         }
 
-        let hash = vaa.body_hash()?;
-        if sig_state.hash != hash {
-            return Err(ProgramError::InvalidAccountData);
+        let expected_custody_id =
+            Bridge::derive_custody_id(program_id, bridge_info.key, &bridge.config.croge_program)?;
+        if expected_custody_id != *custody_info.key {
+            return Err(Error::InvalidDerivedAccount.into());
         }
-
-        let signature_count = (sig_state
-            .signatures
-            .iter()
-            .filter(|v| v.iter().filter(|v| **v != 0).count() != 0)
-            .count() as u8);
-        // Check quorum
-        // We're using a fixed point number transformation with 1 decimal to deal with rounding.
-        // The cast to u16 exists to prevent issues where len_keys * 10 might overflow.
-        if (signature_count as u16) < ((((guardian_set.len_keys as u16) * 10 / 3) * 2) / 10 + 1) {
+        if *destination_info.key != withdrawal.to {
             return Err(ProgramError::InvalidArgument);
         }
+        let destination = Self::token_account_deserialize(destination_info)?;
+        if destination.mint != bridge.config.croge_program {
+            return Err(Error::TokenMintMismatch.into());
+        }
 
-        let mut evict_signatures = false;
-        let payload = vaa.payload.as_ref().ok_or(Error::InvalidVAAAction)?;
-        match payload {
-            VAABody::UpdateGuardianSet(v) => {
-                let mut bridge_data = bridge_info.try_borrow_mut_data()?;
-                let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
-
-                Self::process_vaa_set_update(
-                    program_id,
-                    accounts,
-                    account_info_iter,
-                    &clock,
-                    bridge_info,
-                    payer_info,
-                    bridge,
-                    guardian_set,
-                    &v,
-                )
-            }
-            VAABody::Transfer(v) => {
-                if v.source_chain == CHAIN_ID_SOLANA {
-                    Self::process_vaa_transfer_post(
-                        program_id,
-                        account_info_iter,
-                        bridge_info,
-                        vaa,
-                        &v,
-                        vaa_data,
-                        sig_info.key,
-                    )
-                } else {
-                    let bridge_data = bridge_info.try_borrow_data()?;
-                    let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
-                    evict_signatures = true;
-                    Self::process_vaa_transfer(
-                        program_id,
-                        accounts,
-                        account_info_iter,
-                        bridge_info,
-                        bridge,
-                        &v,
-                    )
-                }
-            }
-            VAABody::UpgradeContract(v) => {
-                if v.chain_id == CHAIN_ID_SOLANA {
-                    evict_signatures = true;
-                    Self::process_vaa_upgrade(
-                        program_id,
-                        accounts,
-                        bridge_info,
-                        v,
-                    )
-                } else {
-                    return Err(Error::InvalidChain.into());
-                }
-            }
-        }?;
-
-        // Check and create claim
-        let claim_seeds = Bridge::derive_claim_seeds(bridge_info.key, vaa.signature_body()?);
-        Bridge::check_and_create_account::<ClaimedVAA>(
+        Self::transfer_swap_back(
             program_id,
             accounts,
-            claim_info.key,
-            payer_info,
-            program_id,
-            &claim_seeds,
-            Some(bridge_info),
+            bridge_info,
+            bridge,
+            custody_info,
+            destination_info,
+            fee_info,
+            withdrawal.amount,
+            withdrawal.fee,
         )?;
 
-        // If the signatures are not needed anymore, evict them and reclaim rent.
-        // This should cover most of the costs of the guardian.
-        if evict_signatures {
-            Self::transfer_sol(sig_info, payer_info, sig_info.lamports())?;
-        }
-
-        // Refund tx fee if possible
-        if bridge_info.lamports().checked_sub(Self::MIN_BRIDGE_BALANCE).unwrap_or(0) >= Self::VAA_TX_FEE {
-            Self::transfer_sol(bridge_info, payer_info, Self::VAA_TX_FEE)?;
-        }
-
-        // Load claim account
-        let mut claim_data = claim_info.try_borrow_mut_data()?;
-        let claim: &mut ClaimedVAA = Bridge::unpack_unchecked(&mut claim_data)?;
-        if claim.is_initialized {
-            return Err(Error::VAAClaimed.into());
-        }
-
-        // Set claimed
-        claim.is_initialized = true;
-        claim.vaa_time = clock.unix_timestamp as u32;
+        msg!("swap back {} from chain {} claimed: {}", nonce, from_chain_id, withdrawal.amount);
 
         Ok(())
     }
 
-    /// Processes a Guardian set update
-    pub fn process_vaa_set_update(
+    /// Drops a held back inbound swap, its tokens stay in custody
+    pub fn process_cancel_pending_withdrawal(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        account_info_iter: &mut Iter<AccountInfo>,
-        clock: &Clock,
-        bridge_info: &AccountInfo,
-        payer_info: &AccountInfo,
-        bridge: &mut Bridge,
-        old_guardian_set: &mut GuardianSet,
-        b: &BodyUpdateGuardianSet,
+        from_chain_id: u32,
+        nonce: U256,
     ) -> ProgramResult {
-        let new_guardian_info = next_account_info(account_info_iter)?;
-
-        // The new guardian set must be signed by the current one
-        if bridge.guardian_set_index != old_guardian_set.index {
-            return Err(Error::OldGuardianSet.into());
-        }
-
-        // The new guardian set must have an index > current
-        // We don't check +1 because we trust the set to not set something close to max(u32)
-        if bridge.guardian_set_index + 1 != b.new_index {
-            return Err(Error::GuardianIndexNotIncreasing.into());
-        }
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let withdrawal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
 
-        // Set the exirity on the old guardian set
-        old_guardian_set.expiration_time =
-            (clock.unix_timestamp as u32) + bridge.config.guardian_set_expiration_time;
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
 
-        // Check whether the new guardian set was derived correctly
-        let guardian_seed = Bridge::derive_guardian_set_seeds(bridge_info.key, b.new_index);
-        Bridge::check_and_create_account::<GuardianSet>(
+        let withdrawal = Self::close_pending_withdrawal(
             program_id,
-            accounts,
-            new_guardian_info.key,
+            bridge_info,
+            withdrawal_info,
             payer_info,
-            program_id,
-            &guardian_seed,
-            None,
+            from_chain_id,
+            nonce,
         )?;
 
-        let mut guardian_set_new_data = new_guardian_info.try_borrow_mut_data()?;
-        let guardian_set_new: &mut GuardianSet =
-            Bridge::unpack_unchecked(&mut guardian_set_new_data)?;
-
-        // The new guardian set must not exist
-        if guardian_set_new.is_initialized {
-            return Err(Error::AlreadyExists.into());
-        }
+        msg!(
+            "swap back {} from chain {} cancelled: {}",
+            nonce,
+            from_chain_id,
+            withdrawal.amount
+        );
 
-        if b.new_keys.len() == 0 {
-            return Err(Error::InvalidVAAFormat.into());
-        }
+        Ok(())
+    }
 
-        if b.new_keys.len() > MAX_LEN_GUARDIAN_KEYS {
-            return Err(Error::InvalidVAAFormat.into());
-        }
+    /// Sets the lamports refunded to the relayer of every inbound swap
+    pub fn process_set_relayer_refund(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        refund: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
 
-        // Set values on the new guardian set
-        guardian_set_new.is_initialized = true;
-        // Force the new guardian set to not expire
-        guardian_set_new.expiration_time = 0;
-        guardian_set_new.index = b.new_index;
-        let mut new_guardians = [[0u8; 20]; MAX_LEN_GUARDIAN_KEYS];
-        for n in 0..b.new_keys.len() {
-            new_guardians[n] = b.new_keys[n]
-        }
-        guardian_set_new.keys = new_guardians;
-        guardian_set_new.len_keys = b.new_keys.len() as u8;
-        guardian_set_new.creation_time = clock.unix_timestamp as u32;
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
 
-        // Update the bridge guardian set id
-        bridge.guardian_set_index = b.new_index;
+        bridge.config.relayer_refund = refund;
 
         Ok(())
     }
 
-    /// Processes a VAA transfer in
-    pub fn process_vaa_transfer(
+    /// Sets the lamports that are left in the bridge when fees are withdrawn
+    pub fn process_set_fee_floor(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        account_info_iter: &mut Iter<AccountInfo>,
-        bridge_info: &AccountInfo,
-        bridge: &Bridge,
-        b: &BodyTransfer,
+        floor: u64,
     ) -> ProgramResult {
-        next_account_info(account_info_iter)?; // Token program
-        let mint_info = next_account_info(account_info_iter)?;
-        let destination_info = next_account_info(account_info_iter)?;
-
-        let destination = Self::token_account_deserialize(destination_info)?;
-        if destination.mint != *mint_info.key {
-            return Err(Error::TokenMintMismatch.into());
-        }
-
-        if b.asset.chain == CHAIN_ID_SOLANA {
-            let custody_info = next_account_info(account_info_iter)?;
-            let expected_custody_id =
-                Bridge::derive_custody_id(program_id, bridge_info.key, mint_info.key)?;
-            if expected_custody_id != *custody_info.key {
-                return Err(Error::InvalidDerivedAccount.into());
-            }
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
 
-            // Native Solana asset, transfer from custody
-            Bridge::token_transfer_custody(
-                program_id,
-                accounts,
-                &bridge.config.token_program,
-                custody_info.key,
-                destination_info.key,
-                b.amount,
-            )?;
-        } else {
-            // Foreign chain asset, mint wrapped asset
-            let expected_mint_address = Bridge::derive_wrapped_asset_id(
-                program_id,
-                bridge_info.key,
-                b.asset.chain,
-                b.asset.decimals,
-                b.asset.address,
-            )?;
-            if expected_mint_address != *mint_info.key {
-                return Err(Error::InvalidDerivedAccount.into());
-            }
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
 
-            // This automatically asserts that the mint was created by this account by using
-            // derivated keys
-            Bridge::wrapped_mint_to(
-                program_id,
-                accounts,
-                &bridge.config.token_program,
-                mint_info.key,
-                destination_info.key,
-                b.amount,
-            )?;
-        }
+        bridge.config.fee_floor = floor;
 
         Ok(())
     }
 
-    /// Processes a VAA post for data availability (for Solana -> foreign transfers)
-    pub fn process_vaa_transfer_post(
+    /// Sends collected processed fees from the bridge to the owner of `bridgeFeesAddress`
+    pub fn process_withdraw_fees(
         program_id: &Pubkey,
-        account_info_iter: &mut Iter<AccountInfo>,
-        bridge_info: &AccountInfo,
-        vaa: &VAA,
-        b: &BodyTransfer,
-        vaa_data: VAAData,
-        sig_account: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
     ) -> ProgramResult {
-        msg!("posting VAA");
-        let proposal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let treasury_info = next_account_info(account_info_iter)?;
+        let recipient_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
 
-        // Check whether the proposal was derived correctly
-        let expected_proposal = Bridge::derive_transfer_id(
-            program_id,
-            bridge_info.key,
-            b.asset.chain,
-            b.asset.address,
-            b.target_chain,
-            b.target_address,
-            b.source_address,
-            b.nonce,
-        )?;
-        if expected_proposal != *proposal_info.key {
-            return Err(Error::InvalidDerivedAccount.into());
-        }
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
 
-        let mut transfer_data = proposal_info.try_borrow_mut_data()?;
-        let mut proposal: &mut TransferOutProposal = Self::unpack(&mut transfer_data)?;
-        if !proposal.matches_vaa(b) {
-            return Err(Error::VAAProposalMismatch.into());
-        }
-        if proposal.vaa_time != 0 {
-            return Err(Error::VAAAlreadySubmitted.into());
+        // The treasury is a token account, its lamports go to the wallet that owns it
+        if *treasury_info.key != bridge.config.bridgeFeesAddress {
+            return Err(ProgramError::InvalidArgument);
         }
-        if vaa_data.len() > MAX_VAA_SIZE {
-            return Err(Error::VAATooLong.into());
+        let treasury = Self::token_account_deserialize(treasury_info)?;
+        if *recipient_info.key != treasury.owner {
+            return Err(ProgramError::InvalidArgument);
         }
 
-        // Set vaa
-        for i in 0..vaa_data.len() {
-            proposal.vaa[i] = vaa_data[i]
+        // The bridge stays rent exempt and keeps the floor to pay refunds
+        let floor = Self::MIN_BRIDGE_BALANCE
+            .checked_add(bridge.config.fee_floor)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let surplus = bridge_info.lamports().saturating_sub(floor);
+        let amount = if amount == 0 { surplus } else { amount };
+        if amount > surplus {
+            return Err(Error::BelowFeeFloor.into());
         }
-        // Stop byte
-        proposal.vaa[vaa_data.len()] = 0xff;
-        proposal.vaa_time = vaa.timestamp;
-        proposal.signature_account = *sig_account;
+
+        Self::transfer_sol(bridge_info, recipient_info, amount)?;
+
+        msg!("withdrew {} lamports of fees", amount);
 
         Ok(())
     }
 
-    /// Processes a VAA contract upgrade
-    pub fn process_vaa_upgrade(
+    /// Sets the weights the token fees are split by
+    pub fn process_set_fee_split(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        bridge_info: &AccountInfo,
-        b: &BodyContractUpgrade,
+        payload: &FeeSplitPayload,
     ) -> ProgramResult {
-        // Invoke upgrade
-        let upgrade_ix = solana_program::bpf_loader_upgradeable::upgrade(
-            program_id,
-            &b.buffer,
-            bridge_info.key,
-            bridge_info.key,
-        );
-        Self::invoke_as_bridge(program_id, &upgrade_ix, accounts);
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        let beneficiaries = &payload.beneficiaries;
+        if beneficiaries.len() > MAX_FEE_BENEFICIARIES {
+            return Err(Error::InvalidFeeSplit.into());
+        }
+        for (i, beneficiary) in beneficiaries.iter().enumerate() {
+            if beneficiary.weight == 0
+                || beneficiary.account == bridge.config.bridgeFeesAddress
+                || beneficiaries[..i].iter().any(|b| b.account == beneficiary.account)
+            {
+                return Err(Error::InvalidFeeSplit.into());
+            }
+
+            // Fees are paid out in the bridged token, a beneficiary has to be able to hold it
+            let beneficiary_info = next_account_info(account_info_iter)?;
+            if *beneficiary_info.key != beneficiary.account {
+                return Err(ProgramError::InvalidArgument);
+            }
+            let beneficiary_account = Self::token_account_deserialize(beneficiary_info)?;
+            if beneficiary_account.mint != bridge.config.croge_program {
+                return Err(Error::TokenMintMismatch.into());
+            }
+        }
+
+        let mut split_beneficiaries = [FeeBeneficiary::default(); MAX_FEE_BENEFICIARIES];
+        split_beneficiaries[..beneficiaries.len()].copy_from_slice(beneficiaries);
+        bridge.config.fee_split = FeeSplit {
+            treasury_weight: payload.treasury_weight,
+            beneficiaries: split_beneficiaries,
+            len_beneficiaries: beneficiaries.len() as u8,
+        };
 
         Ok(())
     }
 
-    /// Creates a new wrapped asset
-    pub fn process_create_wrapped(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        a: &AssetMeta,
-    ) -> ProgramResult {
-        msg!("create wrapped");
+    /// Splits the collected token fees between the treasury and the beneficiaries
+    pub fn process_distribute_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
-        next_account_info(account_info_iter)?; // System program
         next_account_info(account_info_iter)?; // Token program
-        next_account_info(account_info_iter)?; // Rent sysvar
         let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
-        let payer_info = next_account_info(account_info_iter)?;
-        let mint_info = next_account_info(account_info_iter)?;
-        let wrapped_meta_info = next_account_info(account_info_iter)?;
+        let fee_custody_info = next_account_info(account_info_iter)?;
+        let treasury_info = next_account_info(account_info_iter)?;
 
-        let bridge_data = bridge_info.data.try_borrow().map_err(|_| ProgramError::AccountBorrowFailed)?;
+        let bridge_data = bridge_info.try_borrow_data()?;
         let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
 
-        // Foreign chain asset, mint wrapped asset
-        let expected_mint_address = Bridge::derive_wrapped_asset_id(
-            program_id,
-            bridge_info.key,
-            a.chain,
-            a.decimals,
-            a.address,
-        )?;
-        if expected_mint_address != *mint_info.key {
-            return Err(Error::InvalidDerivedAccount.into());
+        // Verify bridge key because it owns the fee custody account
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
         }
 
-        // Create wrapped mint
-        Self::create_wrapped_mint(
+        let expected_fee_custody_id = Bridge::derive_fee_custody_id(
             program_id,
-            accounts,
-            &bridge.config.token_program,
-            mint_info.key,
             bridge_info.key,
-            payer_info,
-            &a,
-            a.decimals,
-            None,
+            &bridge.config.croge_program,
         )?;
+        if *fee_custody_info.key != expected_fee_custody_id {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+        if *treasury_info.key != bridge.config.bridgeFeesAddress {
+            return Err(ProgramError::InvalidArgument);
+        }
 
-        // Check and create wrapped asset meta to allow reverse resolution of info
-        let wrapped_meta_seeds = Bridge::derive_wrapped_meta_seeds(bridge_info.key, mint_info.key);
-        Bridge::check_and_create_account::<WrappedAssetMeta>(
-            program_id,
-            accounts,
-            wrapped_meta_info.key,
-            payer_info,
-            program_id,
-            &wrapped_meta_seeds,
-            None,
-        )?;
+        let fee_custody = Self::token_account_deserialize(fee_custody_info)?;
+        let split = &bridge.config.fee_split;
+        let (shares, treasury_share) = split.shares(fee_custody.amount);
+
+        for (i, share) in shares[..split.len_beneficiaries as usize].iter().enumerate() {
+            let beneficiary_info = next_account_info(account_info_iter)?;
+            if *beneficiary_info.key != split.beneficiaries[i].account {
+                return Err(ProgramError::InvalidArgument);
+            }
+            if *share > 0 {
+                Bridge::token_transfer_custody(
+                    program_id,
+                    accounts,
+                    &spl_token::id(),
+                    fee_custody_info.key,
+                    beneficiary_info.key,
+                    Amount::from(*share),
+                )?;
+            }
+        }
 
-        let mut wrapped_meta_data = wrapped_meta_info.try_borrow_mut_data()?;
-        let wrapped_meta: &mut WrappedAssetMeta = Bridge::unpack_unchecked(&mut wrapped_meta_data)?;
+        if treasury_share > 0 {
+            Bridge::token_transfer_custody(
+                program_id,
+                accounts,
+                &spl_token::id(),
+                fee_custody_info.key,
+                treasury_info.key,
+                Amount::from(treasury_share),
+            )?;
+        }
 
-        wrapped_meta.is_initialized = true;
-        wrapped_meta.address = a.address;
-        wrapped_meta.chain = a.chain;
+        msg!("distributed {} in fees, {} to the treasury", fee_custody.amount, treasury_share);
 
         Ok(())
     }
-}
 
-/// Implementation of actions
-impl Bridge {
-    /// Burn a wrapped asset from account
-    pub fn wrapped_burn(
+    /// Sets the amount above which inbound swaps are held back and for how long
+    pub fn process_set_withdrawal_delay(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_program_id: &Pubkey,
-        token_account: &Pubkey,
-        mint_account: &Pubkey,
-        amount: U256,
-    ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::burn(
-            token_program_id,
-            token_account,
-            mint_account,
-            &Self::derive_bridge_id(program_id)?,
-            &[],
-            amount.as_u64(),
-        )?;
-        Self::invoke_as_bridge(program_id, &ix, accounts)
-    }
+        threshold: U256,
+        delay: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
 
-    /// Mint a wrapped asset to account
-    pub fn wrapped_mint_to(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        token_program_id: &Pubkey,
-        mint: &Pubkey,
-        destination: &Pubkey,
-        amount: U256,
-    ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::mint_to(
-            token_program_id,
-            mint,
-            destination,
-            &Self::derive_bridge_id(program_id)?,
-            &[],
-            amount.as_u64(),
-        )?;
-        Self::invoke_as_bridge(program_id, &ix, accounts)
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        bridge.config.large_withdrawal_threshold = threshold;
+        bridge.config.withdrawal_delay = delay;
+
+        Ok(())
     }
 
-    /// Transfer tokens from a caller
-    pub fn token_transfer_caller(
+    /// Sets the fee the bridge keeps from inbound swaps
+    pub fn process_set_bridge_fee(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_program_id: &Pubkey,
-        source: &Pubkey,
-        destination: &Pubkey,
-        authority: &Pubkey,
-        amount: U256,
-    ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::transfer(
-            token_program_id,
-            source,
-            destination,
-            authority,
-            &[],
-            amount.as_u64(),
-        )?;
-        Self::invoke_as_bridge(program_id, &ix, accounts)
+        fee: &BridgeFee,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        fee.validate()?;
+        bridge.config.bridge_fee = *fee;
+
+        Ok(())
     }
 
-    /// Transfer tokens from a custody account
-    pub fn token_transfer_custody(
+    /// Queues a config change that can be executed once the timelock delay has elapsed
+    pub fn process_queue_config_change(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_program_id: &Pubkey,
-        source: &Pubkey,
-        destination: &Pubkey,
-        amount: U256,
-    ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::transfer(
-            token_program_id,
-            source,
-            destination,
-            &Self::derive_bridge_id(program_id)?,
-            &[],
-            amount.as_u64(),
+        change: ConfigChange,
+        mut pending: PendingChange,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let pending_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        // A queued change has to be executed or cancelled before the next one
+        let pending_seeds = Bridge::derive_pending_change_seeds(bridge_info.key, change);
+        Bridge::check_and_create_or_reuse_account::<PendingChange>(
+            program_id,
+            accounts,
+            pending_info,
+            payer_info,
+            &pending_seeds,
+            Error::ChangeAlreadyPending,
         )?;
-        Self::invoke_as_bridge(program_id, &ix, accounts)
+
+        pending.is_initialized = true;
+        pending.eta = clock.unix_timestamp + bridge.config.timelock_delay as i64;
+        pending.payer = *payer_info.key;
+
+        let mut pending_data = pending_info.try_borrow_mut_data()?;
+        let new_pending: &mut PendingChange = Self::unpack_unchecked(&mut pending_data)?;
+        *new_pending = pending;
+
+        msg!(&format!(
+            "config change queued: {:?}, key {}, delay {}, eta {}",
+            change,
+            pending.new_key,
+            pending.new_delay,
+            pending.eta
+        ));
+
+        Ok(())
     }
 
-    /// Create a new account
-    pub fn create_custody_account(
+    /// Applies a queued config change after its timelock delay
+    pub fn process_execute_config_change(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_program: &Pubkey,
-        bridge: &Pubkey,
-        account: &Pubkey,
-        mint: &Pubkey,
-        payer: &AccountInfo,
-        subsidizer: Option<&AccountInfo>,
-    ) -> Result<(), ProgramError> {
-        Self::check_and_create_account::<[u8; spl_token::state::Account::LEN]>(
+        change: ConfigChange,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let pending_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // The fee custody is derived from the token, fees of the old token have to be
+        // distributed before the token is changed
+        if change == ConfigChange::CrogeProgram {
+            let fee_custody_info = next_account_info(account_info_iter)?;
+            let expected_fee_custody_id = Bridge::derive_fee_custody_id(
+                program_id,
+                bridge_info.key,
+                &bridge.config.croge_program,
+            )?;
+            if *fee_custody_info.key != expected_fee_custody_id {
+                return Err(Error::InvalidDerivedAccount.into());
+            }
+            if !fee_custody_info.data_is_empty()
+                && Self::token_account_deserialize(fee_custody_info)?.amount > 0
+            {
+                return Err(Error::FeeCustodyNotEmpty.into());
+            }
+        }
+
+        let pending = Self::close_pending_change(
             program_id,
-            accounts,
-            account,
-            payer,
-            token_program,
-            &Self::derive_custody_seeds(bridge, mint),
-            subsidizer,
-        )?;
-        msg!(token_program.to_string().as_str());
-        let ix = spl_token::instruction::initialize_account(
-            token_program,
-            account,
-            mint,
-            &Self::derive_bridge_id(program_id)?,
+            bridge_info,
+            pending_info,
+            payer_info,
+            change,
         )?;
-        invoke_signed(&ix, accounts, &[])
+        if clock.unix_timestamp < pending.eta {
+            return Err(Error::TimelockNotElapsed.into());
+        }
+
+        bridge.config.apply(change, &pending);
+
+        msg!(
+            "config change executed: {:?}, key {}, delay {}",
+            change,
+            pending.new_key,
+            pending.new_delay
+        );
+
+        Ok(())
     }
 
-    /// Create a mint for a wrapped asset
-    pub fn create_wrapped_mint(
+    /// Drops a queued config change before its timelock delay has elapsed
+    pub fn process_cancel_config_change(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        token_program: &Pubkey,
-        mint: &Pubkey,
-        bridge: &Pubkey,
-        payer: &AccountInfo,
-        asset: &AssetMeta,
-        decimals: u8,
-        subsidizer: Option<&AccountInfo>,
-    ) -> Result<(), ProgramError> {
-        Self::check_and_create_account::<[u8; spl_token::state::Mint::LEN]>(
+        change: ConfigChange,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let pending_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        let pending = Self::close_pending_change(
             program_id,
-            accounts,
-            mint,
-            payer,
-            token_program,
-            &Self::derive_wrapped_asset_seeds(bridge, asset.chain, asset.decimals, asset.address),
-            subsidizer,
-        )?;
-        let ix = spl_token::instruction::initialize_mint(
-            token_program,
-            mint,
-            &Self::derive_bridge_id(program_id)?,
-            None,
-            decimals,
+            bridge_info,
+            pending_info,
+            payer_info,
+            change,
         )?;
-        invoke_signed(&ix, accounts, &[])
+        if clock.unix_timestamp >= pending.eta {
+            return Err(Error::TimelockElapsed.into());
+        }
+
+        msg!("config change cancelled: {:?}", change);
+
+        Ok(())
     }
 
-    pub fn invoke_as_bridge<'a>(
+    /// Pauses or resumes all swaps
+    pub fn process_update_bridging_status(
         program_id: &Pubkey,
-        instruction: &Instruction,
-        account_infos: &[AccountInfo<'a>],
+        accounts: &[AccountInfo],
+        paused: bool,
     ) -> ProgramResult {
-        let (_, seeds) =
-            Self::find_program_address(&vec!["bridge".as_bytes().to_vec()], program_id);
-        Self::invoke_vec_seed(program_id, instruction, account_infos, &seeds)
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_pause_authority(program_id, bridge_info, bridge, authority_info, paused)?;
+
+        bridge._isBridgingPaused = paused;
+
+        msg!("bridging paused: {}", paused);
+
+        Ok(())
     }
 
-    pub fn invoke_vec_seed<'a>(
+    /// Sets or removes the key that may pause swaps
+    pub fn process_set_pauser(
         program_id: &Pubkey,
-        instruction: &Instruction,
-        account_infos: &[AccountInfo<'a>],
-        seeds: &Vec<Vec<u8>>,
+        accounts: &[AccountInfo],
+        pauser: &Pubkey,
     ) -> ProgramResult {
-        let s: Vec<_> = seeds.iter().map(|item| item.as_slice()).collect();
-        invoke_signed(instruction, account_infos, &[s.as_slice()])
-    }
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
 
-    /// The amount of sol that needs to be held in the BridgeConfig account in order to make it
-    /// exempt of rent payments.
-    const MIN_BRIDGE_BALANCE: u64 = (((solana_program::rent::ACCOUNT_STORAGE_OVERHEAD + size_of::<Bridge>() as u64) *
-        solana_program::rent::DEFAULT_LAMPORTS_PER_BYTE_YEAR) as f64
-        * solana_program::rent::DEFAULT_EXEMPTION_THRESHOLD) as u64;
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
 
-    /// Check that a key was derived correctly and create account
-    pub fn check_and_create_account<T: Sized>(
+        bridge.config.pauser = *pauser;
+
+        msg!("pauser: {}", pauser);
+
+        Ok(())
+    }
+
+    /// Creates the multisig or replaces its signers
+    pub fn process_set_multisig(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        new_account: &Pubkey,
-        payer: &AccountInfo,
-        owner: &Pubkey,
-        seeds: &Vec<Vec<u8>>,
-        subsidizer: Option<&AccountInfo>,
-    ) -> Result<Vec<Vec<u8>>, ProgramError> {
-        msg!("deriving key");
-        let (expected_key, full_seeds) = Bridge::derive_key(program_id, seeds)?;
-        if expected_key != *new_account {
-            return Err(Error::InvalidDerivedAccount.into());
+        payload: &MultisigPayload,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let multisig_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        let signers = &payload.signers;
+        if signers.is_empty() || signers.len() > MAX_MULTISIG_SIGNERS {
+            return Err(Error::InvalidMultisig.into());
+        }
+        if payload.threshold == 0 || payload.threshold as usize > signers.len() {
+            return Err(Error::InvalidMultisig.into());
+        }
+        for (i, signer) in signers.iter().enumerate() {
+            if signers[..i].contains(signer) {
+                return Err(Error::InvalidMultisig.into());
+            }
         }
 
-        msg!("deploying contract");
-        Self::create_account_raw::<T>(
+        if multisig_info.data_is_empty() {
+            let multisig_seeds = Bridge::derive_multisig_seeds(bridge_info.key);
+            Bridge::check_and_create_account::<Multisig>(
+                program_id,
+                accounts,
+                multisig_info.key,
+                payer_info,
+                program_id,
+                &multisig_seeds,
+                None,
+            )?;
+        } else {
+            if multisig_info.owner != program_id {
+                return Err(Error::InvalidOwner.into());
+            }
+            let expected_multisig_key = Self::derive_multisig_id(program_id, bridge_info.key)?;
+            if *multisig_info.key != expected_multisig_key {
+                return Err(Error::InvalidDerivedAccount.into());
+            }
+        }
+
+        let mut multisig_data = multisig_info.try_borrow_mut_data()?;
+        let multisig: &mut Multisig = Self::unpack_unchecked(&mut multisig_data)?;
+        multisig.is_initialized = true;
+        multisig.signers = [Pubkey::default(); MAX_MULTISIG_SIGNERS];
+        multisig.signers[..signers.len()].copy_from_slice(signers);
+        multisig.len_signers = signers.len() as u8;
+        multisig.threshold = payload.threshold;
+        multisig.version += 1;
+
+        msg!("multisig version {}: {} of {}", multisig.version, payload.threshold, signers.len());
+
+        Ok(())
+    }
+
+    /// Creates a proposal of the multisig, approved by the proposer
+    pub fn process_propose_admin_action(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        action: &[u8],
+        action_accounts: &[AccountMeta],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let multisig_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let proposal_info = next_account_info(account_info_iter)?;
+        let signer_info = next_account_info(account_info_iter)?;
+
+        let mut multisig_data = multisig_info.try_borrow_mut_data()?;
+        let multisig: &mut Multisig = Self::unpack(&mut multisig_data)?;
+        let signer_index = Self::check_multisig_signer(
+            program_id,
+            bridge_info,
+            multisig_info,
+            multisig,
+            signer_info,
+        )?;
+
+        // Only well-formed instructions can be proposed
+        if action.len() > MAX_PROPOSAL_DATA || action_accounts.len() > MAX_PROPOSAL_ACCOUNTS {
+            return Err(Error::ProposalTooLong.into());
+        }
+        BridgeInstruction::deserialize(action)?;
+
+        let index = multisig.proposal_count;
+        multisig.proposal_count += 1;
+
+        let proposal_seeds = Bridge::derive_proposal_seeds(bridge_info.key, index);
+        Bridge::check_and_create_account::<Proposal>(
             program_id,
             accounts,
-            new_account,
-            payer.key,
-            owner,
-            &full_seeds,
+            proposal_info.key,
+            signer_info,
+            program_id,
+            &proposal_seeds,
+            None,
         )?;
 
-        // The subsidizer refunds the rent that needs to be paid to create the account.
-        // This mechanism is intended to reduce the cost of operating a guardian.
-        // The subsidizer account should be of the type BridgeConfig and will only pay out
-        // the subsidy if the account holds at least MIN_BRIDGE_BALANCE+rent
-        match subsidizer {
-            None => {}
-            Some(v) => {
-                let bal = v.try_lamports()?;
-                let rent = Rent::default().minimum_balance(size_of::<T>());
-                if bal.checked_sub(Self::MIN_BRIDGE_BALANCE).ok_or(ProgramError::InsufficientFunds)? >= rent {
-                    // Refund rent to payer
-                    Self::transfer_sol(v, payer, rent)?;
-                }
+        let mut proposal_data = proposal_info.try_borrow_mut_data()?;
+        let proposal: &mut Proposal = Self::unpack_unchecked(&mut proposal_data)?;
+        proposal.is_initialized = true;
+        proposal.index = index;
+        proposal.multisig_version = multisig.version;
+        proposal.proposer = *signer_info.key;
+        proposal.approvals[signer_index] = true;
+        proposal.data[..action.len()].copy_from_slice(action);
+        proposal.data_len = action.len() as u16;
+        for (account, meta) in proposal.accounts.iter_mut().zip(action_accounts) {
+            *account = ProposalAccount::from(meta);
+        }
+        proposal.accounts_len = action_accounts.len() as u8;
+
+        msg!("proposal {} created by {}", index, signer_info.key);
+
+        Ok(())
+    }
+
+    /// Adds the approval of a multisig signer to a proposal
+    pub fn process_approve_proposal(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let multisig_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let proposal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let signer_info = next_account_info(account_info_iter)?;
+
+        let multisig_data = multisig_info.try_borrow_data()?;
+        let multisig: &Multisig = Self::unpack_immutable(&multisig_data)?;
+        let signer_index = Self::check_multisig_signer(
+            program_id,
+            bridge_info,
+            multisig_info,
+            multisig,
+            signer_info,
+        )?;
+
+        let mut proposal_data = proposal_info.try_borrow_mut_data()?;
+        let proposal: &mut Proposal =
+            Self::check_proposal(program_id, bridge_info, proposal_info, &mut proposal_data, index)?;
+        if proposal.multisig_version != multisig.version {
+            return Err(Error::ProposalOutdated.into());
+        }
+
+        proposal.approvals[signer_index] = true;
+
+        msg!(&format!(
+            "proposal {} approved by {}: {} of {}",
+            index,
+            signer_info.key,
+            proposal.approval_count(),
+            multisig.threshold
+        ));
+
+        Ok(())
+    }
+
+    /// Executes a proposal that has enough approvals by invoking the bridge as the multisig
+    pub fn process_execute_proposal(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let multisig_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let proposal_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        next_account_info(account_info_iter)?; // Bridge program
+        let action_keys: Vec<&Pubkey> = account_info_iter.map(|account| account.key).collect();
+
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let expected_multisig_key = Self::derive_multisig_id(program_id, bridge_info.key)?;
+        if *multisig_info.key != expected_multisig_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        // The borrows have to end before the proposed instruction accesses the same accounts
+        let (action, action_accounts) = {
+            let multisig_data = multisig_info.try_borrow_data()?;
+            let multisig: &Multisig = Self::unpack_immutable(&multisig_data)?;
+            let mut proposal_data = proposal_info.try_borrow_mut_data()?;
+            let proposal: &mut Proposal = Self::check_proposal(
+                program_id,
+                bridge_info,
+                proposal_info,
+                &mut proposal_data,
+                index,
+            )?;
+            if proposal.multisig_version != multisig.version {
+                return Err(Error::ProposalOutdated.into());
+            }
+            if proposal.approval_count() < multisig.threshold as usize {
+                return Err(Error::NotEnoughApprovals.into());
             }
+
+            // The approvals cover the proposed accounts, no others may be substituted
+            let action_accounts = proposal.account_metas();
+            if action_keys.len() != action_accounts.len()
+                || action_keys.iter().zip(&action_accounts).any(|(key, meta)| **key != meta.pubkey)
+            {
+                return Err(Error::InvalidProposalAccounts.into());
+            }
+
+            proposal.is_executed = true;
+            (proposal.data[..proposal.data_len as usize].to_vec(), action_accounts)
+        };
+
+        let instruction = Instruction {
+            program_id: *program_id,
+            accounts: action_accounts,
+            data: action,
+        };
+        let (_, multisig_seeds) =
+            Self::derive_key(program_id, &Bridge::derive_multisig_seeds(bridge_info.key))?;
+        Self::invoke_vec_seed(&instruction, accounts, &multisig_seeds)?;
+
+        msg!("proposal {} executed", index);
+
+        Ok(())
+    }
+
+    /// Proposes a new key for a role. The key has to accept the role before the proposal expires.
+    pub fn process_propose_role(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        role: Role,
+        new_key: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_owner(program_id, bridge_info, bridge, owner_info)?;
+
+        // A new proposal replaces the previous one
+        let pending = bridge.pending_role_mut(role);
+        pending.key = *new_key;
+        pending.expiry = clock.unix_timestamp + PendingRole::EXPIRY;
+        pending.is_pending = true;
+
+        msg!("{:?} change proposed: {}, expires at {}", role, new_key, pending.expiry);
+
+        Ok(())
+    }
+
+    /// Hands a role over to the proposed key, which has to sign
+    pub fn process_accept_role(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        role: Role,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let new_key_info = next_account_info(account_info_iter)?;
+
+        let clock = Clock::from_account_info(clock_info)?;
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
         }
 
-        Ok(full_seeds)
+        if !new_key_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pending = bridge.pending_role_mut(role);
+        if !pending.is_pending {
+            return Err(Error::NoPendingRoleChange.into());
+        }
+        if pending.key != *new_key_info.key {
+            return Err(Error::InvalidPendingRole.into());
+        }
+        if clock.unix_timestamp > pending.expiry {
+            return Err(Error::RoleChangeExpired.into());
+        }
+
+        *pending = PendingRole::default();
+        *bridge.role_key_mut(role) = *new_key_info.key;
+
+        msg!("{:?} changed: {}", role, new_key_info.key);
+
+        Ok(())
     }
 
-    /// Create a new account
-    fn create_account_raw<T: Sized>(
+    /// Withdraws a pending role change
+    pub fn process_cancel_role_change(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        new_account: &Pubkey,
-        payer: &Pubkey,
-        owner: &Pubkey,
-        seeds: &Vec<Vec<u8>>,
-    ) -> Result<(), ProgramError> {
-        let size = size_of::<T>();
-        let ix = create_account(
-            payer,
-            new_account,
-            Rent::default().minimum_balance(size as usize),
-            size as u64,
-            owner,
-        );
-        let s: Vec<_> = seeds.iter().map(|item| item.as_slice()).collect();
-        invoke_signed(&ix, accounts, &[s.as_slice()])
+        role: Role,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let owner_info = next_account_info(account_info_iter)?;
+
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_owner(program_id, bridge_info, bridge, owner_info)?;
+
+        let pending = bridge.pending_role_mut(role);
+        if !pending.is_pending {
+            return Err(Error::NoPendingRoleChange.into());
+        }
+        *pending = PendingRole::default();
+
+        msg!("{:?} change cancelled", role);
+
+        Ok(())
     }
 
-    /// Get the next account info from the iterator and check that it has the given owner
-    pub fn next_account_info_with_owner<'a, 'b, I: Iterator<Item=&'a AccountInfo<'b>>>(
-        iter: &mut I,
-        owner: &Pubkey,
-    ) -> Result<I::Item, ProgramError> {
-        let acc = iter.next().ok_or(ProgramError::NotEnoughAccountKeys)?;
-        if acc.owner != owner {
-            return Err(Error::InvalidOwner.into());
+    /// Logs the fee the bridge keeps from inbound swaps
+    pub fn process_get_bridge_fee(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        let fee = &bridge.config.bridge_fee;
+
+        msg!("bridge fee: {} bps, min {}, max {}", fee.bps, fee.min_fee, fee.max_fee);
+
+        Ok(())
+    }
+
+    /// Logs the fee the bridge would keep from an inbound swap of `amount` from a chain, as
+    /// `process_swap_back` calculates it
+    pub fn process_fee_calculation(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        amount: U256,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let chain_data = chain_info.try_borrow_data()?;
+        let chain: &ChainState = Self::unpack_immutable(&chain_data)?;
+
+        let (local_amount, _) = chain.decimals.to_local(Amount(amount))?;
+        let fee = chain
+            .inbound_fee
+            .resolve_bridge_fee(&bridge.config)
+            .calculate(local_amount.0)?;
+
+        msg!("fee for {}: {}", amount, fee);
+
+        Ok(())
+    }
+
+    /// Enables or disables swaps with a chain, creating its state account if needed
+    pub fn process_set_chain_enabled(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        enabled: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        Self::check_and_create_chain_state(
+            program_id,
+            accounts,
+            bridge_info,
+            chain_info,
+            payer_info,
+            chain_id,
+        )?;
+
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        // Amounts of a chain with unknown decimals would be paid out at the wrong scale
+        if enabled && !chain.decimals.is_set {
+            return Err(Error::InvalidDecimals.into());
         }
-        Ok(acc)
+        chain.is_enabled = enabled;
+
+        Ok(())
+    }
+
+    /// Pauses or resumes swaps with a chain in one direction
+    pub fn process_set_chain_paused(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        direction: SwapDirection,
+        paused: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let authority_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_pause_authority(program_id, bridge_info, bridge, authority_info, paused)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        // The swap held by the breaker must not be released without a decision of the governor
+        if !paused
+            && direction == SwapDirection::Inbound
+            && chain.inbound_breaker.is_tripped
+            && !chain.inbound_breaker.is_approved
+        {
+            return Err(Error::TrippedSwapNotReviewed.into());
+        }
+        *chain.paused_mut(direction) = paused;
+
+        msg!("chain {} {:?} paused: {}", chain_id, direction, paused);
+
+        Ok(())
+    }
+
+    /// Approves the swap that tripped the circuit breaker of a chain, or rejects it by creating its
+    /// receipt
+    pub fn process_review_tripped_swap(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        approve: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+        let clock = Clock::from_account_info(clock_info)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        let breaker = &mut chain.inbound_breaker;
+        if !breaker.is_tripped || breaker.is_approved {
+            return Err(Error::BreakerNotTripped.into());
+        }
+
+        if approve {
+            breaker.is_approved = true;
+        } else {
+            Self::create_swap_back_receipt(
+                program_id,
+                accounts,
+                bridge_info,
+                receipt_info,
+                governor_info,
+                &clock,
+                chain_id,
+                breaker.tripped_nonce,
+            )?;
+            breaker.is_tripped = false;
+        }
+
+        msg!(&format!(
+            "chain {} tripped swap {} approved: {}",
+            chain_id, breaker.tripped_nonce, approve
+        ));
+
+        Ok(())
+    }
+
+    /// Sets the volume limit of a chain in one direction
+    pub fn process_set_rate_limit(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        direction: SwapDirection,
+        max_amount: U256,
+        window: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        // Every bucket has to span at least one second
+        if !max_amount.is_zero() && (window as usize) < RATE_LIMIT_BUCKETS {
+            return Err(Error::InvalidRateLimit.into());
+        }
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        let limit = chain.limit_mut(direction);
+        if limit.window != window {
+            *limit = RateLimit::default();
+        }
+        limit.max_amount = max_amount;
+        limit.window = window;
+
+        msg!(&format!("chain {} {:?} limit: {} per {}s", chain_id, direction, max_amount, window));
+
+        Ok(())
+    }
+
+    /// Sets the thresholds at which inbound swaps of a chain pause themselves
+    pub fn process_set_circuit_breaker(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        max_amount: U256,
+        max_hourly_amount: U256,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        let breaker = &mut chain.inbound_breaker;
+        breaker.max_amount = max_amount;
+        if breaker.hourly_volume.window != CircuitBreaker::HOUR {
+            breaker.hourly_volume = RateLimit::default();
+            breaker.hourly_volume.window = CircuitBreaker::HOUR;
+        }
+        breaker.hourly_volume.max_amount = max_hourly_amount;
+
+        msg!(
+            "chain {} circuit breaker: {} per swap, {} per hour",
+            chain_id,
+            max_amount,
+            max_hourly_amount
+        );
+
+        Ok(())
+    }
+
+    /// Sets the minimum and maximum amount of a single swap to a chain
+    pub fn process_set_swap_bounds(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        min_amount: U256,
+        max_amount: U256,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        if !max_amount.is_zero() && min_amount > max_amount {
+            return Err(Error::InvalidAmountBounds.into());
+        }
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        chain.min_amount = min_amount;
+        chain.max_amount = max_amount;
+
+        msg!("chain {} swap bounds: min {}, max {}", chain_id, min_amount, max_amount);
+
+        Ok(())
+    }
+
+    /// Logs the minimum and maximum amount of a single swap to a chain
+    pub fn process_get_swap_bounds(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let chain_data = chain_info.try_borrow_data()?;
+        let chain: &ChainState = Self::unpack_immutable(&chain_data)?;
+
+        msg!(
+            "swap bounds for chain {}: min {}, max {}",
+            chain_id,
+            chain.min_amount,
+            chain.max_amount
+        );
+
+        Ok(())
+    }
+
+    /// Sets the format of recipient addresses on a chain
+    pub fn process_set_chain_address_format(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        format: AddressFormat,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        chain.address_format = format;
+
+        msg!("chain {} address format: {:?}", chain_id, format);
+
+        Ok(())
+    }
+
+    /// Sets the decimals of the token on a chain, next to the decimals of the mint
+    pub fn process_set_chain_decimals(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        decimals: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        if *mint_info.key != bridge.config.croge_program {
+            return Err(Error::TokenMintMismatch.into());
+        }
+        let mint = Bridge::mint_deserialize(mint_info)?;
+
+        let mapping = DecimalMapping {
+            foreign_decimals: decimals,
+            local_decimals: mint.decimals,
+            is_set: true,
+        };
+        mapping.validate()?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        if chain.decimals != mapping {
+            // Dust in the old decimals can not be scaled, it stays in custody
+            chain.decimals = mapping;
+            chain.inbound_dust = U256::zero();
+        }
+
+        msg!("chain {} decimals: {}, mint decimals: {}", chain_id, decimals, mint.decimals);
+
+        Ok(())
+    }
+
+    /// Sets the lamport fee for swaps to a chain, creating its state account if needed
+    pub fn process_set_processed_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        fee: U256,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        // The fee is paid in lamports
+        if fee > U256::from(u64::MAX) {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::check_and_create_chain_state(
+            program_id,
+            accounts,
+            bridge_info,
+            chain_info,
+            payer_info,
+            chain_id,
+        )?;
+
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        chain.outbound_fee.processed_fee = fee.as_u64();
+        chain.outbound_fee.has_processed_fee = true;
+
+        Ok(())
+    }
+
+    /// Sets or clears the fee overrides of a chain for one direction, creating its state account
+    /// if needed
+    pub fn process_set_chain_fee(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+        direction: SwapDirection,
+        fee: &ChainFee,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        // The override has to make sense together with the global min and max fee
+        fee.resolve_bridge_fee(&bridge.config).validate()?;
+
+        Self::check_and_create_chain_state(
+            program_id,
+            accounts,
+            bridge_info,
+            chain_info,
+            payer_info,
+            chain_id,
+        )?;
+
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack(&mut chain_data)?;
+        *chain.fee_mut(direction) = *fee;
+
+        Ok(())
+    }
+
+    /// Sets the lamport fee for swaps with chains that don't override it
+    pub fn process_set_default_processed_fee(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        fee: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let governor_info = next_account_info(account_info_iter)?;
+
+        let mut bridge_data = bridge_info.try_borrow_mut_data()?;
+        let bridge: &mut Bridge = Self::unpack(&mut bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        bridge.config.processed_fee = fee;
+
+        Ok(())
+    }
+
+    /// Logs the lamport fee for swaps with a chain
+    pub fn process_get_processed_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        chain_id: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let chain_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+
+        Self::check_chain_state(program_id, bridge_info, chain_info, chain_id)?;
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        let chain_data = chain_info.try_borrow_data()?;
+        let chain: &ChainState = Self::unpack_immutable(&chain_data)?;
+
+        msg!(
+            "processed fee for chain {}: outbound {}, inbound {}",
+            chain_id,
+            chain.outbound_fee.resolve_processed_fee(&bridge.config),
+            chain.inbound_fee.resolve_processed_fee(&bridge.config)
+        );
+
+        Ok(())
+    }
+
+    /// Adds or removes the fee exemption of a wallet
+    pub fn process_exclude_from_fees(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        wallet: &Pubkey,
+        excluded: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        next_account_info(account_info_iter)?; // System program
+        let clock_info = next_account_info(account_info_iter)?;
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let exemption_info = next_account_info(account_info_iter)?;
+        let governor_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+
+        let bridge_data = bridge_info.try_borrow_data()?;
+        let bridge: &Bridge = Self::unpack_immutable(&bridge_data)?;
+        Self::check_governor(program_id, bridge_info, bridge, governor_info)?;
+
+        if excluded {
+            let clock = Clock::from_account_info(clock_info)?;
+            let exemption_seeds = Bridge::derive_fee_exemption_seeds(bridge_info.key, wallet);
+            Bridge::check_and_create_or_reuse_account::<FeeExemption>(
+                program_id,
+                accounts,
+                exemption_info,
+                payer_info,
+                &exemption_seeds,
+                Error::AlreadyExists,
+            )?;
+
+            let mut exemption_data = exemption_info.try_borrow_mut_data()?;
+            let exemption: &mut FeeExemption = Self::unpack_unchecked(&mut exemption_data)?;
+            exemption.is_initialized = true;
+            exemption.wallet = *wallet;
+            exemption.added_by = *governor_info.key;
+            exemption.added_time = clock.unix_timestamp as u32;
+            exemption.payer = *payer_info.key;
+        } else {
+            let expected_exemption_key =
+                Bridge::derive_fee_exemption_id(program_id, bridge_info.key, wallet)?;
+            if *exemption_info.key != expected_exemption_key {
+                return Err(Error::InvalidDerivedAccount.into());
+            }
+            // There is nothing to remove from an exemption that was never created or that has
+            // been wiped and funded again, unpacking fails for the latter
+            if exemption_info.owner != program_id {
+                return Err(Error::UninitializedState.into());
+            }
+
+            // Wipe the exemption and refund its rent to its payer
+            {
+                let mut exemption_data = exemption_info.try_borrow_mut_data()?;
+                let exemption: &mut FeeExemption = Self::unpack(&mut exemption_data)?;
+                if *payer_info.key != exemption.payer {
+                    return Err(ProgramError::InvalidArgument);
+                }
+                *exemption = FeeExemption::default();
+            }
+            Self::transfer_sol(exemption_info, payer_info, exemption_info.lamports())?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs whether an inbound swap has been processed
+    pub fn process_get_bridge_status(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        nonce: U256,
+        from_chain_id: u32,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let bridge_info = Self::next_account_info_with_owner(account_info_iter, program_id)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+
+        let expected_receipt_key =
+            Bridge::derive_swap_back_receipt_id(program_id, bridge_info.key, from_chain_id, nonce)?;
+        if *receipt_info.key != expected_receipt_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        let processed = !receipt_info.data_is_empty() && receipt_info.owner == program_id;
+        msg!("swap {} from chain {} processed: {}", nonce, from_chain_id, processed);
+
+        Ok(())
+    }
+
+
+    /// Verify that a certain fee was sent to the bridge in the preceding instruction
+    pub fn check_fees(instructions_info: &AccountInfo, bridge_info: &AccountInfo, fee: u64) -> Result<(), ProgramError> {
+        let current_instruction = solana_program::sysvar::instructions::load_current_index(
+            &instructions_info.try_borrow_mut_data()?,
+        );
+        if current_instruction == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // The previous ix must be a transfer instruction
+        let transfer_ix_index = (current_instruction - 1) as u8;
+        let transfer_ix = solana_program::sysvar::instructions::load_instruction_at(
+            transfer_ix_index as usize,
+            &instructions_info.try_borrow_mut_data()?,
+        )
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+
+        // Check that the instruction is actually for the system program
+        if transfer_ix.program_id != solana_program::system_program::id() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if transfer_ix.accounts.len() != 2 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Check that the fee was transferred to the bridge config.
+        // We only care that the fee was sent to the bridge, not by whom it was sent.
+        if transfer_ix.accounts[1].pubkey != *bridge_info.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // The transfer instruction is serialized using bincode (little endian)
+        // uint32 ix_type = 2 (Transfer)
+        // uint64 lamports
+        // LEN: 4 + 8 = 12 bytes
+        if transfer_ix.data.len() != 12 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Verify action
+        if transfer_ix.data[..4] != [2, 0, 0, 0] {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Parse amount
+        let mut fixed_data = [0u8; 8];
+        fixed_data.copy_from_slice(&transfer_ix.data[4..]);
+        let amount = u64::from_le_bytes(fixed_data);
+
+        // Verify fee amount
+        if amount < fee {
+            return Err(Error::InsufficientFees.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn transfer_sol(
+        payer_account: &AccountInfo,
+        recipient_account: &AccountInfo,
+        amount: u64,
+    ) -> ProgramResult {
+        let mut payer_balance = payer_account.try_borrow_mut_lamports()?;
+        **payer_balance = payer_balance.checked_sub(amount).ok_or(ProgramError::InsufficientFunds)?;
+        let mut recipient_balance = recipient_account.try_borrow_mut_lamports()?;
+        **recipient_balance = recipient_balance.checked_add(amount).ok_or(ProgramError::InvalidArgument)?;
+
+        Ok(())
+    }
+}
+
+/// Implementation of actions
+impl Bridge {
+    /// Transfer tokens from a caller
+    pub fn token_transfer_caller(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        authority: &Pubkey,
+        amount: Amount,
+    ) -> Result<(), ProgramError> {
+        let ix = spl_token::instruction::transfer(
+            token_program_id,
+            source,
+            destination,
+            authority,
+            &[],
+            amount.to_token_amount()?,
+        )?;
+        Self::invoke_as_bridge(program_id, &ix, accounts)
+    }
+
+    /// Transfer tokens from a custody account
+    pub fn token_transfer_custody(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        token_program_id: &Pubkey,
+        source: &Pubkey,
+        destination: &Pubkey,
+        amount: Amount,
+    ) -> Result<(), ProgramError> {
+        let ix = spl_token::instruction::transfer(
+            token_program_id,
+            source,
+            destination,
+            &Self::derive_bridge_id(program_id)?,
+            &[],
+            amount.to_token_amount()?,
+        )?;
+        Self::invoke_as_bridge(program_id, &ix, accounts)
+    }
+
+    /// Create a new account
+    pub fn create_custody_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        token_program: &Pubkey,
+        bridge: &Pubkey,
+        account: &Pubkey,
+        mint: &Pubkey,
+        payer: &AccountInfo,
+        subsidizer: Option<&AccountInfo>,
+    ) -> Result<(), ProgramError> {
+        Self::check_and_create_account::<[u8; spl_token::state::Account::LEN]>(
+            program_id,
+            accounts,
+            account,
+            payer,
+            token_program,
+            &Self::derive_custody_seeds(bridge, mint),
+            subsidizer,
+        )?;
+        msg!(token_program.to_string().as_str());
+        let ix = spl_token::instruction::initialize_account(
+            token_program,
+            account,
+            mint,
+            &Self::derive_bridge_id(program_id)?,
+        )?;
+        invoke_signed(&ix, accounts, &[])
+    }
+
+    pub fn invoke_as_bridge<'a>(
+        program_id: &Pubkey,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo<'a>],
+    ) -> ProgramResult {
+        let (_, seeds) = Self::find_program_address(&Self::derive_bridge_seeds(), program_id);
+        Self::invoke_vec_seed(instruction, account_infos, &seeds)
+    }
+
+    pub fn invoke_vec_seed<'a>(
+        instruction: &Instruction,
+        account_infos: &[AccountInfo<'a>],
+        seeds: &[Vec<u8>],
+    ) -> ProgramResult {
+        let s: Vec<_> = seeds.iter().map(|item| item.as_slice()).collect();
+        invoke_signed(instruction, account_infos, &[s.as_slice()])
+    }
+
+    /// The amount of sol that needs to be held in the BridgeConfig account in order to make it
+    /// exempt of rent payments.
+    const MIN_BRIDGE_BALANCE: u64 = (((solana_program::rent::ACCOUNT_STORAGE_OVERHEAD + size_of::<Bridge>() as u64) *
+        solana_program::rent::DEFAULT_LAMPORTS_PER_BYTE_YEAR) as f64
+        * solana_program::rent::DEFAULT_EXEMPTION_THRESHOLD) as u64;
+
+    /// Check that a key was derived correctly and create account
+    pub fn check_and_create_account<T: Sized>(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        new_account: &Pubkey,
+        payer: &AccountInfo,
+        owner: &Pubkey,
+        seeds: &[Vec<u8>],
+        subsidizer: Option<&AccountInfo>,
+    ) -> Result<Vec<Vec<u8>>, ProgramError> {
+        msg!("deriving key");
+        let (expected_key, full_seeds) = Bridge::derive_key(program_id, seeds)?;
+        if expected_key != *new_account {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        msg!("deploying contract");
+        Self::create_account_raw::<T>(
+            accounts,
+            new_account,
+            payer.key,
+            owner,
+            &full_seeds,
+        )?;
+
+        // The subsidizer refunds the rent that needs to be paid to create the account.
+        // This mechanism is intended to reduce the cost of operating a guardian.
+        // The subsidizer account should be of the type BridgeConfig and will only pay out
+        // the subsidy if the account holds at least MIN_BRIDGE_BALANCE+rent
+        match subsidizer {
+            None => {}
+            Some(v) => {
+                let bal = v.try_lamports()?;
+                let rent = Rent::default().minimum_balance(size_of::<T>());
+                if bal.checked_sub(Self::MIN_BRIDGE_BALANCE).ok_or(ProgramError::InsufficientFunds)? >= rent {
+                    // Refund rent to payer
+                    Self::transfer_sol(v, payer, rent)?;
+                }
+            }
+        }
+
+        Ok(full_seeds)
+    }
+
+    /// Create a derived account that is closed by wiping it, or reuse it if it is free. Closing
+    /// moves all lamports out, but anyone can send lamports back in the same transaction, which
+    /// keeps the wiped account alive. An account owned by this program that is not initialized is
+    /// therefore free. Fails with `exists` if the account is in use.
+    pub fn check_and_create_or_reuse_account<T: IsInitialized>(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        account_info: &AccountInfo,
+        payer: &AccountInfo,
+        seeds: &[Vec<u8>],
+        exists: Error,
+    ) -> ProgramResult {
+        if account_info.data_is_empty() {
+            Self::check_and_create_account::<T>(
+                program_id,
+                accounts,
+                account_info.key,
+                payer,
+                program_id,
+                seeds,
+                None,
+            )?;
+            return Ok(());
+        }
+
+        let (expected_key, _) = Bridge::derive_key(program_id, seeds)?;
+        if expected_key != *account_info.key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+        if account_info.owner != program_id {
+            return Err(exists.into());
+        }
+        {
+            let data = account_info.try_borrow_data()?;
+            let state: &T = Self::unpack_unchecked_immutable(&data)?;
+            if state.is_initialized() {
+                return Err(exists.into());
+            }
+        }
+
+        // Top the account up to the rent it would have been created with
+        let rent = Rent::default().minimum_balance(size_of::<T>());
+        let balance = account_info.lamports();
+        if balance < rent {
+            invoke_signed(&transfer(payer.key, account_info.key, rent - balance), accounts, &[])?;
+        }
+
+        Ok(())
+    }
+
+    /// Create a new account
+    fn create_account_raw<T: Sized>(
+        accounts: &[AccountInfo],
+        new_account: &Pubkey,
+        payer: &Pubkey,
+        owner: &Pubkey,
+        seeds: &[Vec<u8>],
+    ) -> Result<(), ProgramError> {
+        let size = size_of::<T>();
+        let rent = Rent::default().minimum_balance(size as usize);
+        let s: Vec<_> = seeds.iter().map(|item| item.as_slice()).collect();
+
+        // Anyone can send lamports to a derived address before it is created, which would make
+        // create_account fail. In that case fund, allocate and assign the account separately.
+        let balance = accounts
+            .iter()
+            .find(|a| a.key == new_account)
+            .map(|a| a.lamports())
+            .unwrap_or(0);
+        if balance == 0 {
+            let ix = create_account(payer, new_account, rent, size as u64, owner);
+            return invoke_signed(&ix, accounts, &[s.as_slice()]);
+        }
+
+        if balance < rent {
+            invoke_signed(&transfer(payer, new_account, rent - balance), accounts, &[])?;
+        }
+        invoke_signed(&allocate(new_account, size as u64), accounts, &[s.as_slice()])?;
+        invoke_signed(&assign(new_account, owner), accounts, &[s.as_slice()])
+    }
+
+    /// Create the receipt of an inbound swap. Fails if the swap has already been processed.
+    pub fn create_swap_back_receipt(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        bridge_info: &AccountInfo,
+        receipt_info: &AccountInfo,
+        payer_info: &AccountInfo,
+        clock: &Clock,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> ProgramResult {
+        // The receipt is never closed, so any data means the swap was processed before
+        if !receipt_info.data_is_empty() {
+            return Err(Error::SwapAlreadyProcessed.into());
+        }
+
+        let receipt_seeds = Bridge::derive_swap_back_receipt_seeds(bridge_info.key, from_chain_id, nonce);
+        Bridge::check_and_create_account::<SwapBackReceipt>(
+            program_id,
+            accounts,
+            receipt_info.key,
+            payer_info,
+            program_id,
+            &receipt_seeds,
+            None,
+        )?;
+
+        let mut receipt_data = receipt_info.try_borrow_mut_data()?;
+        let receipt: &mut SwapBackReceipt = Self::unpack_unchecked(&mut receipt_data)?;
+        if receipt.is_initialized {
+            return Err(Error::SwapAlreadyProcessed.into());
+        }
+
+        receipt.is_initialized = true;
+        receipt.from_chain_id = from_chain_id;
+        receipt.nonce = nonce;
+        receipt.processed_time = clock.unix_timestamp as u32;
+
+        Ok(())
+    }
+
+    /// Create the idempotency key of an outbound swap, paid for and owned by the sender
+    pub fn create_swap_idempotency(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        bridge_info: &AccountInfo,
+        idempotency_info: &AccountInfo,
+        sender_info: &AccountInfo,
+        key: u64,
+        to_chain_id: u32,
+        nonce: U256,
+    ) -> ProgramResult {
+        // The key is never closed, so any data means it was used before
+        if !idempotency_info.data_is_empty() {
+            return Err(Error::DuplicateSwap.into());
+        }
+
+        let idempotency_seeds =
+            Bridge::derive_swap_idempotency_seeds(bridge_info.key, sender_info.key, key);
+        Bridge::check_and_create_account::<SwapIdempotency>(
+            program_id,
+            accounts,
+            idempotency_info.key,
+            sender_info,
+            program_id,
+            &idempotency_seeds,
+            None,
+        )?;
+
+        let mut idempotency_data = idempotency_info.try_borrow_mut_data()?;
+        let idempotency: &mut SwapIdempotency = Self::unpack_unchecked(&mut idempotency_data)?;
+        if idempotency.is_initialized {
+            return Err(Error::DuplicateSwap.into());
+        }
+
+        idempotency.is_initialized = true;
+        idempotency.sender = *sender_info.key;
+        idempotency.key = key;
+        idempotency.to_chain_id = to_chain_id;
+        idempotency.nonce = nonce;
+
+        Ok(())
+    }
+
+    /// Check whether a wallet is excluded from fees, given its derived fee exemption account
+    pub fn is_excluded_from_fees(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        exemption_info: &AccountInfo,
+        wallet: &Pubkey,
+    ) -> Result<bool, ProgramError> {
+        let expected_exemption_key =
+            Bridge::derive_fee_exemption_id(program_id, bridge_info.key, wallet)?;
+        if *exemption_info.key != expected_exemption_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+        if exemption_info.data_is_empty() || exemption_info.owner != program_id {
+            return Ok(false);
+        }
+
+        // Zeroed data is a removed exemption, see `check_and_create_or_reuse_account`
+        let exemption_data = exemption_info.try_borrow_data()?;
+        let exemption: &FeeExemption = Self::unpack_unchecked_immutable(&exemption_data)?;
+        Ok(exemption.is_initialized && exemption.wallet == *wallet)
+    }
+
+    /// Verify that the bridge key was derived correctly and that its governor signed
+    pub fn check_governor(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        governor_info: &AccountInfo,
+    ) -> ProgramResult {
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !governor_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *governor_info.key != bridge.config.governor {
+            return Err(Error::InvalidGovernor.into());
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the signer may pause or resume swaps. The pauser may only pause, everything else
+    /// needs the governor.
+    pub fn check_pause_authority(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        authority_info: &AccountInfo,
+        paused: bool,
+    ) -> ProgramResult {
+        if !paused || *authority_info.key == bridge.config.governor {
+            return Self::check_governor(program_id, bridge_info, bridge, authority_info);
+        }
+
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        // The default key means that there is no pauser
+        if bridge.config.pauser == Pubkey::default() || *authority_info.key != bridge.config.pauser {
+            return Err(Error::InvalidPauser.into());
+        }
+
+        Ok(())
+    }
+
+    /// Verify that the bridge key was derived correctly and that its owner signed
+    pub fn check_owner(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        owner_info: &AccountInfo,
+    ) -> ProgramResult {
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !owner_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if *owner_info.key != bridge.config.owner {
+            return Err(Error::InvalidBridgeOwner.into());
+        }
+
+        Ok(())
+    }
+
+    /// Transfer an inbound swap out of custody, the fee to the fee custody account and the rest
+    /// to the recipient
+    pub fn transfer_swap_back(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        custody_info: &AccountInfo,
+        destination_info: &AccountInfo,
+        fee_info: &AccountInfo,
+        amount: U256,
+        fee: U256,
+    ) -> ProgramResult {
+        if !fee.is_zero() {
+            let expected_fee_custody_id = Bridge::derive_fee_custody_id(
+                program_id,
+                bridge_info.key,
+                &bridge.config.croge_program,
+            )?;
+            if *fee_info.key != expected_fee_custody_id {
+                return Err(Error::InvalidDerivedAccount.into());
+            }
+            Bridge::token_transfer_custody(
+                program_id,
+                accounts,
+                &spl_token::id(),
+                custody_info.key,
+                fee_info.key,
+                Amount(fee),
+            )?;
+        }
+
+        Bridge::token_transfer_custody(
+            program_id,
+            accounts,
+            &spl_token::id(),
+            custody_info.key,
+            destination_info.key,
+            Amount(amount),
+        )
+    }
+
+    /// Refund `relayer_refund` lamports from the bridge to a relayer, as long as the bridge stays
+    /// rent exempt
+    pub fn refund_relayer(
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        relayer_info: &AccountInfo,
+    ) -> ProgramResult {
+        let refund = bridge.config.relayer_refund;
+        if refund > 0 && bridge_info.lamports().saturating_sub(Self::MIN_BRIDGE_BALANCE) >= refund {
+            Self::transfer_sol(bridge_info, relayer_info, refund)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check that the fee custody account was derived correctly and create it if it does not exist
+    pub fn check_and_create_fee_custody(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        bridge_info: &AccountInfo,
+        bridge: &Bridge,
+        fee_custody_info: &AccountInfo,
+        payer_info: &AccountInfo,
+    ) -> ProgramResult {
+        let mint = &bridge.config.croge_program;
+        let expected_fee_custody_id =
+            Bridge::derive_fee_custody_id(program_id, bridge_info.key, mint)?;
+        if *fee_custody_info.key != expected_fee_custody_id {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        if fee_custody_info.data_is_empty() {
+            Self::check_and_create_account::<[u8; spl_token::state::Account::LEN]>(
+                program_id,
+                accounts,
+                fee_custody_info.key,
+                payer_info,
+                &spl_token::id(),
+                &Self::derive_fee_custody_seeds(bridge_info.key, mint),
+                None,
+            )?;
+            let ix = spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                fee_custody_info.key,
+                mint,
+                bridge_info.key,
+            )?;
+            invoke_signed(&ix, accounts, &[])?;
+        }
+
+        Ok(())
+    }
+
+    /// Wipe a pending withdrawal and refund its rent to its payer. Returns the withdrawal.
+    pub fn close_pending_withdrawal(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        withdrawal_info: &AccountInfo,
+        payer_info: &AccountInfo,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> Result<PendingWithdrawal, ProgramError> {
+        let expected_withdrawal_key =
+            Self::derive_pending_withdrawal_id(program_id, bridge_info.key, from_chain_id, nonce)?;
+        if *withdrawal_info.key != expected_withdrawal_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        let withdrawal = {
+            let mut withdrawal_data = withdrawal_info.try_borrow_mut_data()?;
+            let withdrawal: &mut PendingWithdrawal = Self::unpack(&mut withdrawal_data)?;
+            let held = *withdrawal;
+            *withdrawal = PendingWithdrawal::default();
+            held
+        };
+        if *payer_info.key != withdrawal.payer {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Self::transfer_sol(withdrawal_info, payer_info, withdrawal_info.lamports())?;
+
+        Ok(withdrawal)
+    }
+
+    /// Wipe a pending config change and refund its rent to its payer. Returns the change.
+    pub fn close_pending_change(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        pending_info: &AccountInfo,
+        payer_info: &AccountInfo,
+        change: ConfigChange,
+    ) -> Result<PendingChange, ProgramError> {
+        let expected_pending_key =
+            Self::derive_pending_change_id(program_id, bridge_info.key, change)?;
+        if *pending_info.key != expected_pending_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        let pending = {
+            let mut pending_data = pending_info.try_borrow_mut_data()?;
+            let pending: &mut PendingChange = Self::unpack(&mut pending_data)?;
+            let queued = *pending;
+            *pending = PendingChange::default();
+            queued
+        };
+        if *payer_info.key != pending.payer {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Self::transfer_sol(pending_info, payer_info, pending_info.lamports())?;
+
+        Ok(pending)
+    }
+
+    /// Verify that the multisig was derived correctly and that one of its signers signed. Returns
+    /// the position of the signer.
+    pub fn check_multisig_signer(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        multisig_info: &AccountInfo,
+        multisig: &Multisig,
+        signer_info: &AccountInfo,
+    ) -> Result<usize, ProgramError> {
+        let expected_bridge_key = Self::derive_bridge_id(program_id)?;
+        if *bridge_info.key != expected_bridge_key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let expected_multisig_key = Self::derive_multisig_id(program_id, bridge_info.key)?;
+        if *multisig_info.key != expected_multisig_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        if !signer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        multisig
+            .signer_index(signer_info.key)
+            .ok_or_else(|| Error::InvalidMultisigSigner.into())
+    }
+
+    /// Unpack a proposal after checking that it was derived for the given index and is still open
+    pub fn check_proposal<'a>(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        proposal_info: &AccountInfo,
+        proposal_data: &'a mut [u8],
+        index: u64,
+    ) -> Result<&'a mut Proposal, ProgramError> {
+        let expected_proposal_key = Self::derive_proposal_id(program_id, bridge_info.key, index)?;
+        if *proposal_info.key != expected_proposal_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        let proposal: &mut Proposal = Self::unpack(proposal_data)?;
+        if proposal.is_executed {
+            return Err(Error::ProposalAlreadyExecuted.into());
+        }
+
+        Ok(proposal)
+    }
+
+    /// Verify that the signer is the upgrade authority of this program
+    pub fn check_upgrade_authority(
+        program_id: &Pubkey,
+        program_data_info: &AccountInfo,
+        authority_info: &AccountInfo,
+    ) -> ProgramResult {
+        let loader_id = solana_program::bpf_loader_upgradeable::id();
+        let (expected_program_data_key, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &loader_id);
+        if *program_data_info.key != expected_program_data_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+        if *program_data_info.owner != loader_id {
+            return Err(Error::InvalidOwner.into());
+        }
+
+        if !authority_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Bincode layout of UpgradeableLoaderState::ProgramData: u32 variant (3), u64 slot and
+        // the authority as Option<Pubkey>. An immutable program has no authority.
+        let data = program_data_info.try_borrow_data()?;
+        if data.len() < 45 || data[..4] != [3, 0, 0, 0] || data[12] != 1 {
+            return Err(Error::InvalidUpgradeAuthority.into());
+        }
+        if data[13..45] != authority_info.key.to_bytes() {
+            return Err(Error::InvalidUpgradeAuthority.into());
+        }
+
+        Ok(())
+    }
+
+    /// Check that a chain state account was derived for the given chain
+    pub fn check_chain_state(
+        program_id: &Pubkey,
+        bridge_info: &AccountInfo,
+        chain_info: &AccountInfo,
+        chain_id: u32,
+    ) -> ProgramResult {
+        let expected_chain_key = Self::derive_chain_state_id(program_id, bridge_info.key, chain_id)?;
+        if *chain_info.key != expected_chain_key {
+            return Err(Error::InvalidDerivedAccount.into());
+        }
+
+        Ok(())
+    }
+
+    /// Create the state account of a chain if it does not exist yet. New chains start disabled.
+    pub fn check_and_create_chain_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        bridge_info: &AccountInfo,
+        chain_info: &AccountInfo,
+        payer_info: &AccountInfo,
+        chain_id: u32,
+    ) -> ProgramResult {
+        if !chain_info.data_is_empty() {
+            if chain_info.owner != program_id {
+                return Err(Error::InvalidOwner.into());
+            }
+            return Self::check_chain_state(program_id, bridge_info, chain_info, chain_id);
+        }
+
+        let chain_seeds = Bridge::derive_chain_state_seeds(bridge_info.key, chain_id);
+        Bridge::check_and_create_account::<ChainState>(
+            program_id,
+            accounts,
+            chain_info.key,
+            payer_info,
+            program_id,
+            &chain_seeds,
+            None,
+        )?;
+
+        let mut chain_data = chain_info.try_borrow_mut_data()?;
+        let chain: &mut ChainState = Self::unpack_unchecked(&mut chain_data)?;
+        chain.is_initialized = true;
+        chain.chain_id = chain_id;
+
+        Ok(())
+    }
+
+    /// Get the next account info from the iterator and check that it has the given owner
+    pub fn next_account_info_with_owner<'a, 'b, I: Iterator<Item=&'a AccountInfo<'b>>>(
+        iter: &mut I,
+        owner: &Pubkey,
+    ) -> Result<I::Item, ProgramError> {
+        let acc = iter.next().ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if acc.owner != owner {
+            return Err(Error::InvalidOwner.into());
+        }
+        Ok(acc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::{
+        clock::Epoch,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        sysvar::clock,
+    };
+    use spl_token::{
+        error::TokenError,
+        instruction::TokenInstruction,
+        state::{Account as TokenAccount, AccountState},
+    };
+    use std::{cell::RefCell, convert::TryInto, sync::Once};
+
+    thread_local! {
+        /// Messages logged on this thread
+        static LOGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn last_log() -> Option<String> {
+        LOGS.with(|logs| logs.borrow().last().cloned())
+    }
+
+    /// Stands in for the system and the SPL token program when the processor invokes them. Only
+    /// what the bridge uses is implemented, accounts are never reassigned and other programs are
+    /// not run.
+    struct TestRuntime;
+
+    impl TestRuntime {
+        fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+            **from.lamports.borrow_mut() = from
+                .lamports()
+                .checked_sub(lamports)
+                .ok_or(ProgramError::InsufficientFunds)?;
+            **to.lamports.borrow_mut() += lamports;
+            Ok(())
+        }
+
+        fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+            if !account.data_is_empty() {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            *account.data.borrow_mut() = Box::leak(vec![0; space as usize].into_boxed_slice());
+            Ok(())
+        }
+    }
+
+    impl SyscallStubs for TestRuntime {
+        fn sol_log(&self, message: &str) {
+            println!("{}", message);
+            LOGS.with(|logs| logs.borrow_mut().push(message.to_string()));
+        }
+
+        fn sol_invoke_signed(
+            &self,
+            instruction: &Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> ProgramResult {
+            let account = |i: usize| {
+                account_infos
+                    .iter()
+                    .find(|info| *info.key == instruction.accounts[i].pubkey)
+                    .ok_or(ProgramError::NotEnoughAccountKeys)
+            };
+
+            if instruction.program_id == solana_program::system_program::id() {
+                let (tag, args) = instruction.data.split_at(4);
+                let arg = |i: usize| u64::from_le_bytes(args[i * 8..i * 8 + 8].try_into().unwrap());
+                return match u32::from_le_bytes(tag.try_into().unwrap()) {
+                    0 => {
+                        Self::move_lamports(account(0)?, account(1)?, arg(0))?;
+                        Self::allocate(account(1)?, arg(1))
+                    }
+                    2 => Self::move_lamports(account(0)?, account(1)?, arg(0)),
+                    8 => Self::allocate(account(0)?, arg(0)),
+                    _ => Ok(()),
+                };
+            }
+            if instruction.program_id != spl_token::id() {
+                return Ok(());
+            }
+
+            match TokenInstruction::unpack(&instruction.data)? {
+                TokenInstruction::InitializeAccount => TokenAccount {
+                    mint: *account(1)?.key,
+                    owner: *account(2)?.key,
+                    state: AccountState::Initialized,
+                    ..TokenAccount::default()
+                }
+                .pack_into_slice(&mut account(0)?.data.borrow_mut()),
+                TokenInstruction::Transfer { amount } => {
+                    let mut source = TokenAccount::unpack(&account(0)?.data.borrow())?;
+                    let mut destination = TokenAccount::unpack(&account(1)?.data.borrow())?;
+                    if source.mint != destination.mint {
+                        return Err(TokenError::MintMismatch.into());
+                    }
+                    source.amount = source
+                        .amount
+                        .checked_sub(amount)
+                        .ok_or(TokenError::InsufficientFunds)?;
+                    destination.amount += amount;
+                    source.pack_into_slice(&mut account(0)?.data.borrow_mut());
+                    destination.pack_into_slice(&mut account(1)?.data.borrow_mut());
+                }
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    /// Makes cross-program invocations take effect on the accounts passed to them
+    fn runtime() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            set_syscall_stubs(Box::new(TestRuntime));
+        });
+    }
+
+    /// Lamports and data of an account, lent to the processor as an `AccountInfo`
+    #[derive(Clone)]
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        is_signer: bool,
+    }
+
+    impl TestAccount {
+        fn new(key: Pubkey, owner: Pubkey, lamports: u64, data: Vec<u8>) -> Self {
+            TestAccount {
+                key,
+                owner,
+                lamports,
+                data,
+                is_signer: false,
+            }
+        }
+
+        /// Wallet that signs the transaction
+        fn signer(key: Pubkey) -> Self {
+            TestAccount {
+                is_signer: true,
+                ..Self::new(key, solana_program::system_program::id(), 1_000_000_000, vec![])
+            }
+        }
+
+        /// Account of the given type as `create_account` leaves it: owned by the program, rent
+        /// exempt and zeroed
+        fn allocated<T>(key: Pubkey, program_id: &Pubkey) -> Self {
+            let rent = Rent::default().minimum_balance(size_of::<T>());
+            Self::new(key, *program_id, rent, vec![0; size_of::<T>()])
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                self.is_signer,
+                true,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                false,
+                Epoch::default(),
+            )
+        }
+    }
+
+    fn program_id() -> Pubkey {
+        Pubkey::new(&[42; 32])
+    }
+
+    fn governor() -> Pubkey {
+        Pubkey::new(&[3; 32])
+    }
+
+    fn system_program() -> TestAccount {
+        runtime();
+        TestAccount::new(solana_program::system_program::id(), Pubkey::default(), 1, vec![])
+    }
+
+    fn clock_account(unix_timestamp: i64) -> TestAccount {
+        let mut account = TestAccount::new(
+            clock::id(),
+            solana_program::sysvar::id(),
+            1,
+            vec![0; Clock::size_of()],
+        );
+        Clock {
+            unix_timestamp,
+            ..Clock::default()
+        }
+        .to_account_info(&mut account.info())
+        .unwrap();
+        account
+    }
+
+    fn relayer() -> Pubkey {
+        Pubkey::new(&[7; 32])
+    }
+
+    fn mint() -> Pubkey {
+        Pubkey::new(&[8; 32])
+    }
+
+    const CHAIN_ID: u32 = 2;
+
+    fn bridge_account(program_id: &Pubkey) -> TestAccount {
+        let bridge_key = Bridge::derive_bridge_id(program_id).unwrap();
+        let mut account = TestAccount::allocated::<Bridge>(bridge_key, program_id);
+        let bridge: &mut Bridge = Bridge::unpack_unchecked(&mut account.data).unwrap();
+        bridge.is_initialized = true;
+        bridge.config.governor = governor();
+        bridge.config.system = relayer();
+        bridge.config.croge_program = mint();
+        account
+    }
+
+    /// State of `CHAIN_ID`, enabled and with the same decimals as the mint
+    fn chain_account(program_id: &Pubkey, bridge_key: &Pubkey) -> TestAccount {
+        let chain_key = Bridge::derive_chain_state_id(program_id, bridge_key, CHAIN_ID).unwrap();
+        let mut account = TestAccount::allocated::<ChainState>(chain_key, program_id);
+        let chain: &mut ChainState = Bridge::unpack_unchecked(&mut account.data).unwrap();
+        chain.is_initialized = true;
+        chain.chain_id = CHAIN_ID;
+        chain.is_enabled = true;
+        chain.decimals = DecimalMapping {
+            foreign_decimals: 9,
+            local_decimals: 9,
+            is_set: true,
+        };
+        account
+    }
+
+    fn token_account(key: Pubkey, owner: &Pubkey, amount: u64) -> TestAccount {
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount {
+            mint: mint(),
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        }
+        .pack_into_slice(&mut data);
+        let rent = Rent::default().minimum_balance(data.len());
+        TestAccount::new(key, spl_token::id(), rent, data)
+    }
+
+    /// Accounts of a `SwapBack` of `nonce` from `CHAIN_ID` in instruction order, paying out of a
+    /// custody holding `custody` tokens to an empty token account of `recipient`. The fee custody
+    /// was created by an earlier `Swap`.
+    fn swap_back_accounts(
+        program_id: &Pubkey,
+        nonce: U256,
+        recipient: &Pubkey,
+        custody: u64,
+    ) -> Vec<TestAccount> {
+        let bridge = bridge_account(program_id);
+        let bridge_key = bridge.key;
+        let derived = |key: Result<Pubkey, Error>, owner: &Pubkey| {
+            TestAccount::new(key.unwrap(), *owner, 0, vec![])
+        };
+        vec![
+            system_program(),
+            TestAccount::new(spl_token::id(), Pubkey::default(), 1, vec![]),
+            TestAccount::new(solana_program::sysvar::rent::id(), Pubkey::default(), 1, vec![]),
+            clock_account(1_000),
+            TestAccount::new(
+                solana_program::sysvar::instructions::id(),
+                Pubkey::default(),
+                1,
+                vec![],
+            ),
+            bridge,
+            chain_account(program_id, &bridge_key),
+            derived(
+                Bridge::derive_swap_back_receipt_id(program_id, &bridge_key, CHAIN_ID, nonce),
+                program_id,
+            ),
+            derived(
+                Bridge::derive_pending_withdrawal_id(program_id, &bridge_key, CHAIN_ID, nonce),
+                program_id,
+            ),
+            derived(
+                Bridge::derive_fee_exemption_id(program_id, &bridge_key, recipient),
+                program_id,
+            ),
+            token_account(
+                Bridge::derive_custody_id(program_id, &bridge_key, &mint()).unwrap(),
+                &bridge_key,
+                custody,
+            ),
+            token_account(Pubkey::new(&[10; 32]), recipient, 0),
+            token_account(
+                Bridge::derive_fee_custody_id(program_id, &bridge_key, &mint()).unwrap(),
+                &bridge_key,
+                0,
+            ),
+            TestAccount::signer(relayer()),
+        ]
+    }
+
+    fn sender() -> Pubkey {
+        Pubkey::new(&[11; 32])
+    }
+
+    /// Accounts of the first `Swap` to `CHAIN_ID` in instruction order, taking the tokens from a
+    /// token account of `sender` holding `balance`. An idempotency key of 5 may be used.
+    fn swap_accounts(program_id: &Pubkey, balance: u64) -> Vec<TestAccount> {
+        let bridge = bridge_account(program_id);
+        let bridge_key = bridge.key;
+        let derived = |key: Result<Pubkey, Error>, owner: &Pubkey| {
+            TestAccount::new(key.unwrap(), *owner, 0, vec![])
+        };
+        vec![
+            system_program(),
+            TestAccount::new(spl_token::id(), Pubkey::default(), 1, vec![]),
+            TestAccount::new(solana_program::sysvar::rent::id(), Pubkey::default(), 1, vec![]),
+            clock_account(1_000),
+            TestAccount::new(
+                solana_program::sysvar::instructions::id(),
+                Pubkey::default(),
+                1,
+                vec![],
+            ),
+            token_account(Pubkey::new(&[12; 32]), &sender(), balance),
+            bridge,
+            chain_account(program_id, &bridge_key),
+            derived(
+                Bridge::derive_swap_record_id(program_id, &bridge_key, CHAIN_ID, U256::one()),
+                program_id,
+            ),
+            derived(
+                Bridge::derive_swap_idempotency_id(program_id, &bridge_key, &sender(), 5),
+                program_id,
+            ),
+            TestAccount::new(mint(), spl_token::id(), 1, vec![]),
+            token_account(
+                Bridge::derive_custody_id(program_id, &bridge_key, &mint()).unwrap(),
+                &bridge_key,
+                0,
+            ),
+            derived(
+                Bridge::derive_fee_exemption_id(program_id, &bridge_key, &sender()),
+                program_id,
+            ),
+            derived(
+                Bridge::derive_fee_custody_id(program_id, &bridge_key, &mint()),
+                &spl_token::id(),
+            ),
+            TestAccount::signer(sender()),
+        ]
+    }
+
+    fn swap(program_id: &Pubkey, infos: &[AccountInfo], amount: u64) -> ProgramResult {
+        let payload = SwapPayload {
+            amount: U256::from(amount),
+            toChainID: CHAIN_ID,
+            recipient: [1; 32],
+            idempotency_key: 0,
+            deadline: 0,
+        };
+        Bridge::process_swap(program_id, infos, &payload)
+    }
+
+    fn swap_record(info: &AccountInfo) -> SwapRecord {
+        *Bridge::unpack_immutable::<SwapRecord>(&info.data.borrow()).unwrap()
+    }
+
+    fn set_decimals(account: &mut TestAccount, foreign_decimals: u8, local_decimals: u8) {
+        chain_state_mut(account).decimals = DecimalMapping {
+            foreign_decimals,
+            local_decimals,
+            is_set: true,
+        };
+    }
+
+    fn chain_state_mut(account: &mut TestAccount) -> &mut ChainState {
+        Bridge::unpack(&mut account.data).unwrap()
+    }
+
+    fn chain_state(info: &AccountInfo) -> ChainState {
+        *Bridge::unpack_immutable::<ChainState>(&info.data.borrow()).unwrap()
+    }
+
+    fn infos(accounts: &mut [TestAccount]) -> Vec<AccountInfo<'_>> {
+        accounts.iter_mut().map(TestAccount::info).collect()
+    }
+
+    /// Infos of the accounts at `indices`, in that order
+    fn select<'a>(infos: &[AccountInfo<'a>], indices: &[usize]) -> Vec<AccountInfo<'a>> {
+        indices.iter().map(|i| infos[*i].clone()).collect()
+    }
+
+    fn token_balance(info: &AccountInfo) -> u64 {
+        TokenAccount::unpack(&info.data.borrow()).unwrap().amount
+    }
+
+    #[test]
+    fn reused_account_has_to_be_derived_owned_and_free() {
+        let program_id = program_id();
+        let seeds = vec![b"reuse".to_vec()];
+        let (key, _) = Bridge::derive_key(&program_id, &seeds).unwrap();
+        let mut system = system_program();
+        let mut account = TestAccount::allocated::<FeeExemption>(key, &program_id);
+        let mut payer = TestAccount::signer(Pubkey::new(&[4; 32]));
+        let mut foreign = TestAccount::allocated::<FeeExemption>(key, &Pubkey::new(&[5; 32]));
+        let mut underived = TestAccount::allocated::<FeeExemption>(payer.key, &program_id);
+
+        let accounts = [
+            system.info(),
+            account.info(),
+            payer.info(),
+            foreign.info(),
+            underived.info(),
+        ];
+        let reuse = |account: &AccountInfo| {
+            Bridge::check_and_create_or_reuse_account::<FeeExemption>(
+                &program_id,
+                &accounts,
+                account,
+                &accounts[2],
+                &seeds,
+                Error::AlreadyExists,
+            )
+        };
+
+        assert_eq!(reuse(&accounts[1]), Ok(()));
+        assert_eq!(reuse(&accounts[3]), Err(Error::AlreadyExists.into()));
+        assert_eq!(reuse(&accounts[4]), Err(Error::InvalidDerivedAccount.into()));
+
+        Bridge::unpack_unchecked::<FeeExemption>(&mut accounts[1].data.borrow_mut())
+            .unwrap()
+            .is_initialized = true;
+        assert_eq!(reuse(&accounts[1]), Err(Error::AlreadyExists.into()));
+
+        // Wiped and funded again within the transaction that closed it
+        accounts[1].data.borrow_mut().fill(0);
+        **accounts[1].lamports.borrow_mut() = 1;
+        assert_eq!(reuse(&accounts[1]), Ok(()));
+    }
+
+    #[test]
+    fn fee_exemption_removal_refunds_its_payer() {
+        let program_id = program_id();
+        let wallet = Pubkey::new(&[9; 32]);
+        let mut system = system_program();
+        let mut clock = clock_account(1_000);
+        let mut bridge = bridge_account(&program_id);
+        let exemption_key =
+            Bridge::derive_fee_exemption_id(&program_id, &bridge.key, &wallet).unwrap();
+        let mut exemption = TestAccount::allocated::<FeeExemption>(exemption_key, &program_id);
+        let mut governor = TestAccount::signer(governor());
+        let mut payer = TestAccount::signer(Pubkey::new(&[4; 32]));
+
+        let accounts = [
+            system.info(),
+            clock.info(),
+            bridge.info(),
+            exemption.info(),
+            governor.info(),
+            payer.info(),
+        ];
+        let exempt =
+            |excluded| Bridge::process_exclude_from_fees(&program_id, &accounts, &wallet, excluded);
+        let is_excluded =
+            || Bridge::is_excluded_from_fees(&program_id, &accounts[2], &accounts[3], &wallet);
+
+        assert_eq!(is_excluded(), Ok(false));
+        assert_eq!(exempt(true), Ok(()));
+        assert_eq!(is_excluded(), Ok(true));
+        assert_eq!(exempt(true), Err(Error::AlreadyExists.into()));
+        let rent = accounts[3].lamports();
+        assert_eq!(exempt(false), Ok(()));
+        assert_eq!(accounts[3].lamports(), 0);
+        assert_eq!(accounts[5].lamports(), 1_000_000_000 + rent);
+        assert_eq!(is_excluded(), Ok(false));
+        assert_eq!(exempt(false), Err(Error::UninitializedState.into()));
+    }
+
+    #[test]
+    fn config_change_can_be_queued_again_after_execution_and_refund() {
+        let program_id = program_id();
+        let mut system = system_program();
+        let mut clock = clock_account(1_000);
+        let mut bridge = bridge_account(&program_id);
+        let change = ConfigChange::TimelockDelay;
+        let pending_key =
+            Bridge::derive_pending_change_id(&program_id, &bridge.key, change).unwrap();
+        let mut pending = TestAccount::allocated::<PendingChange>(pending_key, &program_id);
+        let mut governor = TestAccount::signer(governor());
+        let mut payer = TestAccount::signer(Pubkey::new(&[4; 32]));
+
+        let accounts = [
+            system.info(),
+            clock.info(),
+            bridge.info(),
+            pending.info(),
+            governor.info(),
+            payer.info(),
+        ];
+        let queue = || {
+            let pending = PendingChange {
+                new_delay: 60,
+                ..PendingChange::default()
+            };
+            Bridge::process_queue_config_change(&program_id, &accounts, change, pending)
+        };
+        let execute_accounts = [
+            accounts[1].clone(),
+            accounts[2].clone(),
+            accounts[3].clone(),
+            accounts[5].clone(),
+        ];
+
+        assert_eq!(queue(), Ok(()));
+        assert_eq!(queue(), Err(Error::ChangeAlreadyPending.into()));
+        assert_eq!(
+            Bridge::process_execute_config_change(&program_id, &execute_accounts, change),
+            Ok(())
+        );
+        assert_eq!(accounts[3].lamports(), 0);
+
+        **accounts[3].lamports.borrow_mut() = 1;
+        assert_eq!(queue(), Ok(()));
+        assert_eq!(queue(), Err(Error::ChangeAlreadyPending.into()));
+        let data = accounts[3].data.borrow();
+        let queued: &PendingChange = Bridge::unpack_immutable(&data).unwrap();
+        assert_eq!(queued.new_delay, 60);
+        assert_eq!(queued.eta, 1_060);
+    }
+
+    #[test]
+    fn pending_withdrawal_close_refunds_its_payer() {
+        let program_id = program_id();
+        let mut bridge = bridge_account(&program_id);
+        let nonce = U256::from(7);
+        let withdrawal_key =
+            Bridge::derive_pending_withdrawal_id(&program_id, &bridge.key, 2, nonce).unwrap();
+        let mut withdrawal =
+            TestAccount::allocated::<PendingWithdrawal>(withdrawal_key, &program_id);
+        let rent = withdrawal.lamports;
+        let mut payer = TestAccount::signer(Pubkey::new(&[4; 32]));
+        {
+            let held: &mut PendingWithdrawal =
+                Bridge::unpack_unchecked(&mut withdrawal.data).unwrap();
+            held.is_initialized = true;
+            held.amount = U256::from(500);
+            held.payer = payer.key;
+        }
+
+        let accounts = [bridge.info(), withdrawal.info(), payer.info()];
+        let close = || {
+            Bridge::close_pending_withdrawal(
+                &program_id,
+                &accounts[0],
+                &accounts[1],
+                &accounts[2],
+                2,
+                nonce,
+            )
+        };
+
+        assert_eq!(close().map(|held| held.amount), Ok(U256::from(500)));
+        assert_eq!(accounts[1].lamports(), 0);
+        assert_eq!(accounts[2].lamports(), 1_000_000_000 + rent);
+        assert_eq!(close(), Err(Error::UninitializedState.into()));
+    }
+
+    #[test]
+    fn proposal_executes_only_with_the_proposed_accounts() {
+        let program_id = program_id();
+        let mut system = system_program();
+        let mut bridge = bridge_account(&program_id);
+        let multisig_key = Bridge::derive_multisig_id(&program_id, &bridge.key).unwrap();
+        let mut multisig = TestAccount::allocated::<Multisig>(multisig_key, &program_id);
+        let signer_key = Pubkey::new(&[5; 32]);
+        {
+            let state: &mut Multisig = Bridge::unpack_unchecked(&mut multisig.data).unwrap();
+            state.is_initialized = true;
+            state.signers[0] = signer_key;
+            state.len_signers = 1;
+            state.threshold = 1;
+        }
+        let proposal_key = Bridge::derive_proposal_id(&program_id, &bridge.key, 0).unwrap();
+        let mut proposal = TestAccount::new(proposal_key, program_id, 0, vec![]);
+        let mut signer = TestAccount::signer(signer_key);
+        let mut bridge_program = TestAccount::new(program_id, Pubkey::default(), 1, vec![]);
+        let mut other = TestAccount::signer(Pubkey::new(&[6; 32]));
+
+        let action = UpdateBridgingStaus(true).serialize().unwrap();
+        let action_accounts = vec![
+            AccountMeta::new(bridge.key, false),
+            AccountMeta::new_readonly(multisig_key, true),
+        ];
+        let accounts = [
+            system.info(),
+            bridge.info(),
+            multisig.info(),
+            proposal.info(),
+            signer.info(),
+            bridge_program.info(),
+            other.info(),
+        ];
+        assert_eq!(
+            Bridge::process_propose_admin_action(
+                &program_id,
+                &accounts,
+                &action,
+                &action_accounts
+            ),
+            Ok(())
+        );
+
+        // Bridge config, multisig, proposal and bridge program followed by the action accounts
+        let execute = |action_accounts: &[usize]| {
+            let execute_accounts: Vec<AccountInfo> = [1, 2, 3, 5]
+                .iter()
+                .chain(action_accounts)
+                .map(|i| accounts[*i].clone())
+                .collect();
+            Bridge::process_execute_proposal(&program_id, &execute_accounts, 0)
+        };
+        let invalid = Err(Error::InvalidProposalAccounts.into());
+        assert_eq!(execute(&[1, 6]), invalid);
+        assert_eq!(execute(&[1]), invalid);
+        assert_eq!(execute(&[1, 2]), Ok(()));
+        assert_eq!(
+            execute(&[1, 2]),
+            Err(Error::ProposalAlreadyExecuted.into())
+        );
+    }
+
+    #[test]
+    fn replayed_swap_back_does_not_count_towards_the_breaker() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_back_accounts(&program_id, U256::from(7), &recipient, 1_000);
+        {
+            let breaker = &mut chain_state_mut(&mut accounts[6]).inbound_breaker;
+            breaker.hourly_volume.max_amount = U256::from(1_000);
+            breaker.hourly_volume.window = CircuitBreaker::HOUR;
+            breaker.hourly_volume.consume(1_000, U256::from(900)).unwrap();
+            breaker.tripped_nonce = U256::from(3);
+        }
+        let receipt_key = accounts[7].key;
+        accounts[7] = TestAccount::allocated::<SwapBackReceipt>(receipt_key, &program_id);
+        Bridge::unpack_unchecked::<SwapBackReceipt>(&mut accounts[7].data)
+            .unwrap()
+            .is_initialized = true;
+        let bridge_key = accounts[5].key;
+        let next_receipt_key =
+            Bridge::derive_swap_back_receipt_id(&program_id, &bridge_key, CHAIN_ID, U256::from(8))
+                .unwrap();
+        accounts.push(TestAccount::new(next_receipt_key, program_id, 0, vec![]));
+        let mut infos = infos(&mut accounts);
+
+        assert_eq!(
+            swap_back(&program_id, &infos, U256::from(7), 500),
+            Err(Error::SwapAlreadyProcessed.into())
+        );
+        let chain = chain_state(&infos[6]);
+        assert!(!chain.is_inbound_paused);
+        assert_eq!(chain.inbound_breaker.tripped_nonce, U256::from(3));
+
+        // A new swap of the same amount trips the breaker
+        infos.swap(7, 14);
+        assert_eq!(swap_back(&program_id, &infos, U256::from(8), 500), Ok(()));
+        let chain = chain_state(&infos[6]);
+        assert!(chain.is_inbound_paused);
+        assert_eq!(chain.inbound_breaker.tripped_nonce, U256::from(8));
+    }
+
+    fn swap_back(
+        program_id: &Pubkey,
+        infos: &[AccountInfo],
+        nonce: U256,
+        amount: u64,
+    ) -> ProgramResult {
+        let payload = SwapBackPayload {
+            to: *infos[11].key,
+            amount: U256::from(amount),
+            nonce,
+            fromChainID: CHAIN_ID,
+        };
+        Bridge::process_swap_back(program_id, infos, &payload)
+    }
+
+    /// Accounts of a `SwapBack` of `nonce` whose circuit breaker trips at 400 tokens, followed
+    /// by the governor
+    fn breaker_accounts(program_id: &Pubkey, nonce: U256) -> Vec<TestAccount> {
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_back_accounts(program_id, nonce, &recipient, 1_000);
+        accounts.push(TestAccount::signer(governor()));
+        let breaker = &mut chain_state_mut(&mut accounts[6]).inbound_breaker;
+        breaker.max_amount = U256::from(400);
+        breaker.hourly_volume.max_amount = U256::from(1_000);
+        breaker.hourly_volume.window = CircuitBreaker::HOUR;
+        accounts
+    }
+
+    fn review(program_id: &Pubkey, infos: &[AccountInfo], approve: bool) -> ProgramResult {
+        let accounts = select(infos, &[0, 3, 5, 6, 7, 14]);
+        Bridge::process_review_tripped_swap(program_id, &accounts, CHAIN_ID, approve)
+    }
+
+    fn resume_inbound(program_id: &Pubkey, infos: &[AccountInfo]) -> ProgramResult {
+        let accounts = select(infos, &[5, 6, 14]);
+        Bridge::process_set_chain_paused(
+            program_id,
+            &accounts,
+            CHAIN_ID,
+            SwapDirection::Inbound,
+            false,
+        )
+    }
+
+    #[test]
+    fn rejected_tripped_swap_back_is_never_paid_out() {
+        let program_id = program_id();
+        let nonce = U256::from(7);
+        let mut accounts = breaker_accounts(&program_id, nonce);
+        let infos = infos(&mut accounts);
+        assert_eq!(swap_back(&program_id, &infos, nonce, 500), Ok(()));
+        assert!(chain_state(&infos[6]).is_inbound_paused);
+
+        assert_eq!(
+            resume_inbound(&program_id, &infos),
+            Err(Error::TrippedSwapNotReviewed.into())
+        );
+        assert_eq!(review(&program_id, &infos, false), Ok(()));
+        assert_eq!(
+            review(&program_id, &infos, false),
+            Err(Error::BreakerNotTripped.into())
+        );
+        assert_eq!(resume_inbound(&program_id, &infos), Ok(()));
+
+        assert_eq!(
+            swap_back(&program_id, &infos, nonce, 500),
+            Err(Error::SwapAlreadyProcessed.into())
+        );
+        assert_eq!(token_balance(&infos[11]), 0);
+    }
+
+    #[test]
+    fn approved_tripped_swap_back_passes_once_and_is_recorded() {
+        let program_id = program_id();
+        let nonce = U256::from(7);
+        let mut accounts = breaker_accounts(&program_id, nonce);
+        let infos = infos(&mut accounts);
+        assert_eq!(swap_back(&program_id, &infos, nonce, 500), Ok(()));
+
+        assert_eq!(review(&program_id, &infos, true), Ok(()));
+        assert_eq!(
+            review(&program_id, &infos, true),
+            Err(Error::BreakerNotTripped.into())
+        );
+        assert_eq!(resume_inbound(&program_id, &infos), Ok(()));
+
+        assert_eq!(swap_back(&program_id, &infos, nonce, 500), Ok(()));
+        assert_eq!(token_balance(&infos[11]), 500);
+        let breaker = chain_state(&infos[6]).inbound_breaker;
+        assert!(!breaker.is_tripped);
+        assert_eq!(breaker.hourly_volume.used(1_000), U256::from(500));
+    }
+
+    #[test]
+    fn chain_without_decimals_is_unusable() {
+        let program_id = program_id();
+        let mut system = system_program();
+        let mut bridge = bridge_account(&program_id);
+        let chain_key = Bridge::derive_chain_state_id(&program_id, &bridge.key, CHAIN_ID).unwrap();
+        let mut chain = TestAccount::new(chain_key, program_id, 0, vec![]);
+        let mut governor = TestAccount::signer(governor());
+        let accounts = [system.info(), bridge.info(), chain.info(), governor.info()];
+        let enable = |enabled| {
+            let accounts = select(&accounts, &[0, 1, 2, 3, 3]);
+            Bridge::process_set_chain_enabled(&program_id, &accounts, CHAIN_ID, enabled)
+        };
+        assert_eq!(enable(true), Err(Error::InvalidDecimals.into()));
+        assert_eq!(enable(false), Ok(()));
+        assert_eq!(enable(true), Err(Error::InvalidDecimals.into()));
+
+        // A chain that was enabled without decimals rejects swaps in both directions
+        let mut accounts = swap_accounts(&program_id, 1_000);
+        chain_state_mut(&mut accounts[7]).decimals = DecimalMapping::default();
+        assert_eq!(
+            swap(&program_id, &infos(&mut accounts), 100),
+            Err(Error::InvalidDecimals.into())
+        );
+        let mut accounts = swap_back_accounts(&program_id, U256::one(), &sender(), 1_000);
+        chain_state_mut(&mut accounts[6]).decimals = DecimalMapping::default();
+        assert_eq!(
+            swap_back(&program_id, &infos(&mut accounts), U256::one(), 100),
+            Err(Error::InvalidDecimals.into())
+        );
+    }
+
+    #[test]
+    fn swap_is_scaled_to_the_decimals_of_the_chain() {
+        let program_id = program_id();
+        let mut more_decimals = swap_accounts(&program_id, 5_000_000);
+        set_decimals(&mut more_decimals[7], 18, 9);
+        let more_decimals = infos(&mut more_decimals);
+        assert_eq!(swap(&program_id, &more_decimals, 1_500_000), Ok(()));
+        assert_eq!(swap_record(&more_decimals[8]).amount, U256::from(1_500_000) * U256::exp10(9));
+        assert_eq!(token_balance(&more_decimals[11]), 1_500_000);
+
+        // Less than a unit of the destination chain goes to the fees, on top of the bridge fee
+        let mut accounts = swap_accounts(&program_id, 5_000_000);
+        set_decimals(&mut accounts[7], 6, 9);
+        Bridge::unpack::<Bridge>(&mut accounts[6].data)
+            .unwrap()
+            .config
+            .bridge_fee
+            .min_fee = U256::from(1_000);
+        // Accounts are not rolled back after a failed instruction, so it only gets a copy
+        assert_eq!(
+            swap(&program_id, &infos(&mut accounts.clone()), 1_999),
+            Err(Error::InsufficientFees.into())
+        );
+        let infos = infos(&mut accounts);
+        assert_eq!(swap(&program_id, &infos, 1_502_500), Ok(()));
+        assert_eq!(swap_record(&infos[8]).amount, U256::from(1_501));
+        assert_eq!(token_balance(&infos[11]), 1_501_000);
+        assert_eq!(token_balance(&infos[13]), 1_500);
+        assert_eq!(token_balance(&infos[5]), 3_497_500);
+    }
+
+    #[test]
+    fn swap_back_is_scaled_and_dust_is_paid_out_as_fees() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut fewer_decimals = swap_back_accounts(&program_id, U256::one(), &recipient, 10_000);
+        set_decimals(&mut fewer_decimals[6], 6, 9);
+        let fewer_decimals = infos(&mut fewer_decimals);
+        assert_eq!(swap_back(&program_id, &fewer_decimals, U256::one(), 2), Ok(()));
+        assert_eq!(token_balance(&fewer_decimals[11]), 2_000);
+
+        // Each swap truncates 0.6 tokens, the second one completes a token that goes to the fees
+        let mut accounts = Vec::new();
+        for nonce in 1..=2 {
+            let mut swap_accounts =
+                swap_back_accounts(&program_id, U256::from(nonce), &recipient, 1_000);
+            set_decimals(&mut swap_accounts[6], 12, 9);
+            accounts.push(swap_accounts);
+        }
+        let (first, second) = accounts.split_at_mut(1);
+        let first = infos(&mut first[0]);
+        let mut second = infos(&mut second[0]);
+        for i in [5, 6, 10, 11, 12].iter() {
+            second[*i] = first[*i].clone();
+        }
+        assert_eq!(swap_back(&program_id, &first, U256::one(), 100_600), Ok(()));
+        assert_eq!(token_balance(&first[11]), 100);
+        assert_eq!(chain_state(&first[6]).inbound_dust, U256::from(600));
+        assert_eq!(swap_back(&program_id, &second, U256::from(2), 100_600), Ok(()));
+        assert_eq!(token_balance(&first[11]), 200);
+        assert_eq!(token_balance(&first[12]), 1);
+        assert_eq!(token_balance(&first[10]), 799);
+        assert_eq!(chain_state(&first[6]).inbound_dust, U256::from(200));
+    }
+
+    #[test]
+    fn fee_quote_matches_the_swap_back_deduction() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_back_accounts(&program_id, U256::one(), &recipient, 10_000_000);
+        Bridge::unpack::<Bridge>(&mut accounts[5].data)
+            .unwrap()
+            .config
+            .bridge_fee
+            .bps = 10;
+        set_decimals(&mut accounts[6], 12, 9);
+        let chain = chain_state_mut(&mut accounts[6]);
+        chain.inbound_fee.bps = 30;
+        chain.inbound_fee.has_bps = true;
+        let infos = infos(&mut accounts);
+
+        assert_eq!(
+            Bridge::process_fee_calculation(
+                &program_id,
+                &select(&infos, &[5, 6]),
+                CHAIN_ID,
+                U256::from(5_000_000_000u64)
+            ),
+            Ok(())
+        );
+        assert_eq!(last_log(), Some("fee for 5000000000: 15000".to_string()));
+
+        assert_eq!(swap_back(&program_id, &infos, U256::one(), 5_000_000_000), Ok(()));
+        assert_eq!(token_balance(&infos[11]), 5_000_000 - 15_000);
+        assert_eq!(token_balance(&infos[12]), 15_000);
+    }
+
+    #[test]
+    fn swap_locks_tokens_in_custody_and_records_the_swap() {
+        let program_id = program_id();
+        let mut accounts = swap_accounts(&program_id, 50_000);
+        Bridge::unpack::<Bridge>(&mut accounts[6].data)
+            .unwrap()
+            .config
+            .bridge_fee
+            .bps = 100;
+        // The first swap creates the custody account
+        let custody_key = accounts[11].key;
+        accounts[11] = TestAccount::new(custody_key, spl_token::id(), 0, vec![]);
+        let infos = infos(&mut accounts);
+
+        assert_eq!(swap(&program_id, &infos, 10_000), Ok(()));
+        assert_eq!(token_balance(&infos[5]), 40_000);
+        assert_eq!(token_balance(&infos[11]), 9_900);
+        assert_eq!(token_balance(&infos[13]), 100);
+        assert_eq!(chain_state(&infos[7]).nonce, U256::one());
+        assert_eq!(
+            swap_record(&infos[8]),
+            SwapRecord {
+                to_chain_id: CHAIN_ID,
+                nonce: U256::one(),
+                sender: sender(),
+                recipient: [1; 32],
+                amount: U256::from(9_900),
+                lockup_time: 1_000,
+                is_initialized: true,
+            }
+        );
+    }
+
+    #[test]
+    fn swap_back_pays_out_once_and_only_for_the_system_authority() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let nonce = U256::from(7);
+        let mut accounts = swap_back_accounts(&program_id, nonce, &recipient, 50_000);
+        Bridge::unpack::<Bridge>(&mut accounts[5].data)
+            .unwrap()
+            .config
+            .bridge_fee
+            .bps = 100;
+
+        let mut unsigned = accounts.clone();
+        unsigned[13].is_signer = false;
+        assert_eq!(
+            swap_back(&program_id, &infos(&mut unsigned), nonce, 10_000),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        let mut impostor = accounts.clone();
+        impostor[13] = TestAccount::signer(Pubkey::new(&[13; 32]));
+        assert_eq!(
+            swap_back(&program_id, &infos(&mut impostor), nonce, 10_000),
+            Err(Error::InvalidSystem.into())
+        );
+
+        let infos = infos(&mut accounts);
+        assert_eq!(swap_back(&program_id, &infos, nonce, 10_000), Ok(()));
+        assert_eq!(token_balance(&infos[10]), 40_000);
+        assert_eq!(token_balance(&infos[11]), 9_900);
+        assert_eq!(token_balance(&infos[12]), 100);
+        let receipt_data = infos[7].data.borrow();
+        let receipt: &SwapBackReceipt = Bridge::unpack_immutable(&receipt_data).unwrap();
+        assert_eq!((receipt.from_chain_id, receipt.nonce), (CHAIN_ID, nonce));
+        drop(receipt_data);
+
+        assert_eq!(
+            swap_back(&program_id, &infos, nonce, 10_000),
+            Err(Error::SwapAlreadyProcessed.into())
+        );
+        assert_eq!(token_balance(&infos[11]), 9_900);
+    }
+
+    #[test]
+    fn swap_back_waives_the_fee_of_an_exempt_recipient() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_back_accounts(&program_id, U256::one(), &recipient, 50_000);
+        Bridge::unpack::<Bridge>(&mut accounts[5].data)
+            .unwrap()
+            .config
+            .bridge_fee
+            .bps = 100;
+        let exemption_key = accounts[9].key;
+        accounts[9] = TestAccount::allocated::<FeeExemption>(exemption_key, &program_id);
+        let exemption: &mut FeeExemption = Bridge::unpack_unchecked(&mut accounts[9].data).unwrap();
+        exemption.is_initialized = true;
+        exemption.wallet = recipient;
+        let infos = infos(&mut accounts);
+
+        assert_eq!(swap_back(&program_id, &infos, U256::one(), 10_000), Ok(()));
+        assert_eq!(token_balance(&infos[11]), 10_000);
+        assert_eq!(token_balance(&infos[12]), 0);
+    }
+
+    /// Program data account of the upgradeable loader with the given upgrade authority
+    fn program_data_account(program_id: &Pubkey, authority: Option<Pubkey>) -> TestAccount {
+        let loader_id = solana_program::bpf_loader_upgradeable::id();
+        let (key, _) = Pubkey::find_program_address(&[program_id.as_ref()], &loader_id);
+        let mut data = vec![3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        match authority {
+            Some(authority) => {
+                data.push(1);
+                data.extend_from_slice(authority.as_ref());
+            }
+            None => data.push(0),
+        }
+        TestAccount::new(key, loader_id, 1, data)
+    }
+
+    #[test]
+    fn only_the_upgrade_authority_initializes_the_bridge_once() {
+        let program_id = program_id();
+        let deployer = Pubkey::new(&[14; 32]);
+        let payload = InitializePayload {
+            croge_program: mint(),
+            system: relayer(),
+            governor: governor(),
+            bridgeFeesAddress: Pubkey::new(&[15; 32]),
+            owner: Pubkey::new(&[16; 32]),
+            bridge_fee: BridgeFee {
+                bps: 30,
+                ..BridgeFee::default()
+            },
+            processed_fee: 5_000,
+        };
+        let bridge_key = Bridge::derive_bridge_id(&program_id).unwrap();
+        let mut accounts = vec![
+            system_program(),
+            TestAccount::new(bridge_key, program_id, 0, vec![]),
+            program_data_account(&program_id, Some(deployer)),
+            TestAccount::signer(deployer),
+        ];
+        let initialize =
+            |infos: &[AccountInfo]| Bridge::process_initialize(&program_id, infos, &payload);
+
+        let mut unsigned = accounts.clone();
+        unsigned[3].is_signer = false;
+        assert_eq!(
+            initialize(&infos(&mut unsigned)),
+            Err(ProgramError::MissingRequiredSignature)
+        );
+        let mut other = accounts.clone();
+        other[3] = TestAccount::signer(governor());
+        assert_eq!(
+            initialize(&infos(&mut other)),
+            Err(Error::InvalidUpgradeAuthority.into())
+        );
+        let mut immutable = accounts.clone();
+        immutable[2] = program_data_account(&program_id, None);
+        assert_eq!(
+            initialize(&infos(&mut immutable)),
+            Err(Error::InvalidUpgradeAuthority.into())
+        );
+
+        let infos = infos(&mut accounts);
+        assert_eq!(initialize(&infos), Ok(()));
+        let bridge = *Bridge::unpack_immutable::<Bridge>(&infos[1].data.borrow()).unwrap();
+        assert_eq!(bridge.config.croge_program, payload.croge_program);
+        assert_eq!(bridge.config.system, payload.system);
+        assert_eq!(bridge.config.governor, payload.governor);
+        assert_eq!(bridge.config.bridgeFeesAddress, payload.bridgeFeesAddress);
+        assert_eq!(bridge.config.owner, payload.owner);
+        assert_eq!(bridge.config.bridge_fee, payload.bridge_fee);
+        assert_eq!(bridge.config.processed_fee, payload.processed_fee);
+        assert_eq!(initialize(&infos), Err(Error::AlreadyExists.into()));
+    }
+
+    #[test]
+    fn role_is_handed_over_only_to_the_proposed_key_before_expiry() {
+        let program_id = program_id();
+        let owner = Pubkey::new(&[16; 32]);
+        let new_key = Pubkey::new(&[17; 32]);
+        let mut bridge = bridge_account(&program_id);
+        Bridge::unpack::<Bridge>(&mut bridge.data).unwrap().config.owner = owner;
+        let mut clock = clock_account(1_000);
+        let mut late_clock = clock_account(1_001 + PendingRole::EXPIRY);
+        let mut owner = TestAccount::signer(owner);
+        let mut new = TestAccount::signer(new_key);
+        let mut other = TestAccount::signer(Pubkey::new(&[18; 32]));
+        let accounts = [
+            clock.info(),
+            bridge.info(),
+            owner.info(),
+            new.info(),
+            late_clock.info(),
+            other.info(),
+        ];
+        let propose = |role| {
+            let accounts = select(&accounts, &[0, 1, 2]);
+            Bridge::process_propose_role(&program_id, &accounts, role, &new_key)
+        };
+        let accept = |role, indices: &[usize]| {
+            Bridge::process_accept_role(&program_id, &select(&accounts, indices), role)
+        };
+        let cancel = |role| {
+            Bridge::process_cancel_role_change(&program_id, &select(&accounts, &[1, 2]), role)
+        };
+        let config = || {
+            let data = accounts[1].data.borrow();
+            Bridge::unpack_immutable::<Bridge>(&data).unwrap().config
+        };
+
+        assert_eq!(
+            accept(Role::Governor, &[0, 1, 3]),
+            Err(Error::NoPendingRoleChange.into())
+        );
+        assert_eq!(propose(Role::Governor), Ok(()));
+        assert_eq!(config().governor, governor());
+        assert_eq!(
+            accept(Role::Governor, &[0, 1, 5]),
+            Err(Error::InvalidPendingRole.into())
+        );
+        assert_eq!(accept(Role::Governor, &[0, 1, 3]), Ok(()));
+        assert_eq!(config().governor, new_key);
+        assert_eq!(
+            accept(Role::Governor, &[0, 1, 3]),
+            Err(Error::NoPendingRoleChange.into())
+        );
+
+        assert_eq!(propose(Role::Owner), Ok(()));
+        assert_eq!(
+            accept(Role::Owner, &[4, 1, 3]),
+            Err(Error::RoleChangeExpired.into())
+        );
+        assert_eq!(cancel(Role::Owner), Ok(()));
+        assert_eq!(cancel(Role::Owner), Err(Error::NoPendingRoleChange.into()));
+        assert_eq!(
+            accept(Role::Owner, &[0, 1, 3]),
+            Err(Error::NoPendingRoleChange.into())
+        );
+        assert_eq!(config().owner, *accounts[2].key);
+    }
+
+    #[test]
+    fn paused_direction_is_reported_and_the_other_keeps_working() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_accounts(&program_id, 50_000);
+        accounts.push(TestAccount::signer(governor()));
+        let outbound = infos(&mut accounts);
+        let pause = |paused| {
+            let accounts = select(&outbound, &[6, 7, 15]);
+            let direction = SwapDirection::Outbound;
+            Bridge::process_set_chain_paused(&program_id, &accounts, CHAIN_ID, direction, paused)
+        };
+        assert_eq!(pause(true), Ok(()));
+        assert_eq!(swap(&program_id, &outbound, 1_000), Err(Error::OutboundPaused.into()));
+        assert_eq!(pause(false), Ok(()));
+        assert_eq!(swap(&program_id, &outbound, 1_000), Ok(()));
+
+        let mut accounts = swap_back_accounts(&program_id, U256::one(), &recipient, 50_000);
+        chain_state_mut(&mut accounts[6]).is_outbound_paused = true;
+        let mut inbound_paused = accounts.clone();
+        chain_state_mut(&mut inbound_paused[6]).is_inbound_paused = true;
+        assert_eq!(
+            swap_back(&program_id, &infos(&mut inbound_paused), U256::one(), 1_000),
+            Err(Error::InboundPaused.into())
+        );
+        assert_eq!(
+            swap_back(&program_id, &infos(&mut accounts), U256::one(), 1_000),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn pauser_can_pause_but_never_resume() {
+        let program_id = program_id();
+        let pauser = Pubkey::new(&[19; 32]);
+        let mut bridge = bridge_account(&program_id);
+        let mut chain = chain_account(&program_id, &bridge.key);
+        let mut pauser_account = TestAccount::signer(pauser);
+        let mut governor = TestAccount::signer(governor());
+        let accounts = [
+            bridge.info(),
+            chain.info(),
+            pauser_account.info(),
+            governor.info(),
+        ];
+        let pause_bridge = |authority, paused| {
+            let accounts = select(&accounts, &[0, authority]);
+            Bridge::process_update_bridging_status(&program_id, &accounts, paused)
+        };
+        let pause_chain = |authority, paused| {
+            let accounts = select(&accounts, &[0, 1, authority]);
+            let direction = SwapDirection::Inbound;
+            Bridge::process_set_chain_paused(&program_id, &accounts, CHAIN_ID, direction, paused)
+        };
+        let set_pauser = |pauser: &Pubkey| {
+            Bridge::process_set_pauser(&program_id, &select(&accounts, &[0, 3]), pauser)
+        };
+
+        // Without a pauser only the governor pauses
+        assert_eq!(pause_bridge(2, true), Err(Error::InvalidPauser.into()));
+        assert_eq!(set_pauser(&pauser), Ok(()));
+        assert_eq!(
+            Bridge::process_set_pauser(&program_id, &select(&accounts, &[0, 2]), &pauser),
+            Err(Error::InvalidGovernor.into())
+        );
+
+        assert_eq!(pause_bridge(2, true), Ok(()));
+        assert_eq!(pause_bridge(2, false), Err(Error::InvalidGovernor.into()));
+        assert_eq!(pause_bridge(3, false), Ok(()));
+        assert_eq!(pause_chain(2, true), Ok(()));
+        assert_eq!(pause_chain(2, false), Err(Error::InvalidGovernor.into()));
+        assert_eq!(pause_chain(3, false), Ok(()));
+
+        // `RevokePauser` sets the default key
+        assert_eq!(set_pauser(&Pubkey::default()), Ok(()));
+        assert_eq!(pause_chain(2, true), Err(Error::InvalidPauser.into()));
+        assert_eq!(pause_chain(3, true), Ok(()));
+    }
+
+    #[test]
+    fn swap_has_to_be_within_the_bounds_of_the_chain() {
+        let program_id = program_id();
+        let mut accounts = swap_accounts(&program_id, 50_000);
+        accounts.push(TestAccount::signer(governor()));
+        let infos = infos(&mut accounts);
+        let set_bounds = |min: u64, max: u64| {
+            let accounts = select(&infos, &[6, 7, 15]);
+            let (min, max) = (U256::from(min), U256::from(max));
+            Bridge::process_set_swap_bounds(&program_id, &accounts, CHAIN_ID, min, max)
+        };
+
+        assert_eq!(set_bounds(200, 100), Err(Error::InvalidAmountBounds.into()));
+        assert_eq!(set_bounds(100, 10_000), Ok(()));
+        assert_eq!(
+            Bridge::process_get_swap_bounds(&program_id, &select(&infos, &[6, 7]), CHAIN_ID),
+            Ok(())
+        );
+        assert_eq!(
+            last_log(),
+            Some("swap bounds for chain 2: min 100, max 10000".to_string())
+        );
+
+        // Checked before the tokens are locked
+        assert_eq!(swap(&program_id, &infos, 99), Err(Error::AmountTooSmall.into()));
+        assert_eq!(swap(&program_id, &infos, 10_001), Err(Error::AmountTooLarge.into()));
+        assert_eq!(token_balance(&infos[5]), 50_000);
+        assert_eq!(swap(&program_id, &infos, 10_000), Ok(()));
+    }
+
+    #[test]
+    fn swap_recipient_has_to_match_the_address_format_of_the_chain() {
+        let program_id = program_id();
+        let mut accounts = swap_accounts(&program_id, 50_000);
+        chain_state_mut(&mut accounts[7]).address_format = AddressFormat::Evm;
+        let infos = infos(&mut accounts);
+        let swap_to = |recipient| {
+            let payload = SwapPayload {
+                amount: U256::from(1_000),
+                toChainID: CHAIN_ID,
+                recipient,
+                idempotency_key: 0,
+                deadline: 0,
+            };
+            Bridge::process_swap(&program_id, &infos, &payload)
+        };
+        let mut evm_address = [0; 32];
+        evm_address[12..].copy_from_slice(&[0xab; 20]);
+        let mut padded = evm_address;
+        padded[0] = 1;
+
+        assert_eq!(swap_to(padded), Err(Error::InvalidRecipient.into()));
+        assert_eq!(swap_to([0; 32]), Err(Error::InvalidRecipient.into()));
+        assert_eq!(token_balance(&infos[5]), 50_000);
+        assert_eq!(swap_to(evm_address), Ok(()));
+        assert_eq!(swap_record(&infos[8]).recipient, evm_address);
+    }
+
+    #[test]
+    fn expired_and_resubmitted_swaps_are_rejected() {
+        let program_id = program_id();
+        let mut accounts = swap_accounts(&program_id, 50_000);
+        let bridge_key = accounts[6].key;
+        let second_record =
+            Bridge::derive_swap_record_id(&program_id, &bridge_key, CHAIN_ID, U256::from(2))
+                .unwrap();
+        accounts.push(TestAccount::new(second_record, program_id, 0, vec![]));
+        let mut infos = infos(&mut accounts);
+        let swap_with = |infos: &[AccountInfo], idempotency_key, deadline| {
+            let payload = SwapPayload {
+                amount: U256::from(1_000),
+                toChainID: CHAIN_ID,
+                recipient: [1; 32],
+                idempotency_key,
+                deadline,
+            };
+            Bridge::process_swap(&program_id, infos, &payload)
+        };
+
+        // The clock is at 1000
+        assert_eq!(swap_with(&infos, 5, 999), Err(Error::SwapExpired.into()));
+        assert_eq!(swap_with(&infos, 5, 1_000), Ok(()));
+        let idempotency_data = infos[9].data.borrow();
+        let idempotency: &SwapIdempotency = Bridge::unpack_immutable(&idempotency_data).unwrap();
+        assert_eq!((idempotency.sender, idempotency.nonce), (sender(), U256::one()));
+        drop(idempotency_data);
+
+        // The resubmitted swap would get the next nonce
+        infos.swap(8, 15);
+        assert_eq!(swap_with(&infos, 5, 0), Err(Error::DuplicateSwap.into()));
+        assert_eq!(token_balance(&infos[5]), 49_000);
+    }
+
+    #[test]
+    fn fee_withdrawal_goes_to_the_treasury_owner_and_leaves_the_floor() {
+        let program_id = program_id();
+        let treasury_owner = Pubkey::new(&[21; 32]);
+        let treasury_key = Pubkey::new(&[22; 32]);
+        let mut bridge = bridge_account(&program_id);
+        {
+            let config = &mut Bridge::unpack::<Bridge>(&mut bridge.data).unwrap().config;
+            config.bridgeFeesAddress = treasury_key;
+            config.fee_floor = 1_000;
+        }
+        bridge.lamports = Bridge::MIN_BRIDGE_BALANCE + 1_000 + 5_000;
+        let mut treasury = token_account(treasury_key, &treasury_owner, 0);
+        let mut owner = TestAccount::new(treasury_owner, Pubkey::default(), 0, vec![]);
+        let mut governor = TestAccount::signer(governor());
+        let mut other_treasury = token_account(Pubkey::new(&[23; 32]), &treasury_owner, 0);
+        let mut other_wallet = TestAccount::new(Pubkey::new(&[24; 32]), treasury_owner, 0, vec![]);
+        let accounts = [bridge.info(), treasury.info(), owner.info(), governor.info()];
+        let withdraw = |accounts: &[AccountInfo], amount| {
+            Bridge::process_withdraw_fees(&program_id, accounts, amount)
+        };
+
+        // The treasury is a token account, only the wallet owning it receives the lamports
+        let mut wrong_recipient = accounts.clone();
+        wrong_recipient[2] = other_wallet.info();
+        assert_eq!(withdraw(&wrong_recipient, 1), Err(ProgramError::InvalidArgument));
+        let mut wrong_treasury = accounts.clone();
+        wrong_treasury[1] = other_treasury.info();
+        assert_eq!(withdraw(&wrong_treasury, 1), Err(ProgramError::InvalidArgument));
+
+        assert_eq!(withdraw(&accounts, 5_001), Err(Error::BelowFeeFloor.into()));
+        assert_eq!(withdraw(&accounts, 2_000), Ok(()));
+        assert_eq!(accounts[2].lamports(), 2_000);
+        assert_eq!(withdraw(&accounts, 3_000), Ok(()));
+        assert_eq!(accounts[0].lamports(), Bridge::MIN_BRIDGE_BALANCE + 1_000);
+        assert_eq!(accounts[2].lamports(), 5_000);
+        assert_eq!(withdraw(&accounts, 1), Err(Error::BelowFeeFloor.into()));
+
+        // 0 withdraws the whole surplus
+        **accounts[0].lamports.borrow_mut() += 4_000;
+        assert_eq!(withdraw(&accounts, 0), Ok(()));
+        assert_eq!(accounts[0].lamports(), Bridge::MIN_BRIDGE_BALANCE + 1_000);
+        assert_eq!(accounts[2].lamports(), 9_000);
+        assert_eq!(accounts[1].lamports(), Rent::default().minimum_balance(TokenAccount::LEN));
+    }
+
+    #[test]
+    fn fee_beneficiaries_have_to_hold_the_bridged_token() {
+        let program_id = program_id();
+        let owner = Pubkey::new(&[25; 32]);
+        let mut bridge = bridge_account(&program_id);
+        let mut governor = TestAccount::signer(governor());
+        let mut beneficiary = token_account(Pubkey::new(&[26; 32]), &owner, 0);
+        let mut other_mint = token_account(Pubkey::new(&[27; 32]), &owner, 0);
+        let other_mint_account = TokenAccount {
+            mint: Pubkey::new(&[28; 32]),
+            ..TokenAccount::unpack(&other_mint.data).unwrap()
+        };
+        other_mint_account.pack_into_slice(&mut other_mint.data);
+        let accounts = [bridge.info(), governor.info(), beneficiary.info(), other_mint.info()];
+        let set_split = |beneficiaries: &[usize]| {
+            let payload = FeeSplitPayload {
+                treasury_weight: 1,
+                beneficiaries: beneficiaries
+                    .iter()
+                    .map(|i| FeeBeneficiary { account: *accounts[*i].key, weight: 1 })
+                    .collect(),
+            };
+            let mut selected = vec![0, 1];
+            selected.extend_from_slice(beneficiaries);
+            Bridge::process_set_fee_split(&program_id, &select(&accounts, &selected), &payload)
+        };
+
+        assert_eq!(set_split(&[3]), Err(Error::TokenMintMismatch.into()));
+        assert_eq!(set_split(&[2, 3]), Err(Error::TokenMintMismatch.into()));
+        assert_eq!(
+            Bridge::process_set_fee_split(
+                &program_id,
+                &select(&accounts, &[0, 1]),
+                &FeeSplitPayload {
+                    treasury_weight: 1,
+                    beneficiaries: vec![FeeBeneficiary { account: *accounts[2].key, weight: 1 }],
+                },
+            ),
+            Err(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(set_split(&[2]), Ok(()));
+        let bridge_data = accounts[0].data.borrow();
+        let split = &Bridge::unpack_immutable::<Bridge>(&bridge_data).unwrap().config.fee_split;
+        assert_eq!(split.len_beneficiaries, 1);
+        assert_eq!(split.beneficiaries[0].account, *accounts[2].key);
+    }
+
+    #[test]
+    fn token_is_only_changed_once_its_fees_are_distributed() {
+        let program_id = program_id();
+        let new_mint = Pubkey::new(&[29; 32]);
+        let mut system = system_program();
+        let mut clock = clock_account(1_000);
+        let mut bridge = bridge_account(&program_id);
+        let change = ConfigChange::CrogeProgram;
+        let pending_key =
+            Bridge::derive_pending_change_id(&program_id, &bridge.key, change).unwrap();
+        let mut pending = TestAccount::allocated::<PendingChange>(pending_key, &program_id);
+        let mut governor = TestAccount::signer(governor());
+        let mut payer = TestAccount::signer(Pubkey::new(&[4; 32]));
+        let fee_custody_key =
+            Bridge::derive_fee_custody_id(&program_id, &bridge.key, &mint()).unwrap();
+        let mut fee_custody = token_account(fee_custody_key, &bridge.key, 300);
+        let mut other_custody = token_account(Pubkey::new(&[30; 32]), &bridge.key, 0);
+        let accounts = [
+            system.info(),
+            clock.info(),
+            bridge.info(),
+            pending.info(),
+            governor.info(),
+            payer.info(),
+            fee_custody.info(),
+            other_custody.info(),
+        ];
+        let pending = PendingChange {
+            new_key: new_mint,
+            ..PendingChange::default()
+        };
+        assert_eq!(
+            Bridge::process_queue_config_change(&program_id, &accounts[..6], change, pending),
+            Ok(())
+        );
+        let execute = |fee_custody| {
+            let accounts = select(&accounts, &[1, 2, 3, 5, fee_custody]);
+            Bridge::process_execute_config_change(&program_id, &accounts, change)
+        };
+
+        assert_eq!(execute(7), Err(Error::InvalidDerivedAccount.into()));
+        assert_eq!(execute(6), Err(Error::FeeCustodyNotEmpty.into()));
+
+        // Distributing the fees empties the custody
+        let distributed = TokenAccount {
+            amount: 0,
+            ..TokenAccount::unpack(&accounts[6].data.borrow()).unwrap()
+        };
+        distributed.pack_into_slice(&mut accounts[6].data.borrow_mut());
+        assert_eq!(execute(6), Ok(()));
+        let bridge_data = accounts[2].data.borrow();
+        let bridge: &Bridge = Bridge::unpack_immutable(&bridge_data).unwrap();
+        assert_eq!(bridge.config.croge_program, new_mint);
+    }
+
+    #[test]
+    fn relayer_refund_leaves_the_minimum_bridge_balance() {
+        let program_id = program_id();
+        let recipient = Pubkey::new(&[9; 32]);
+        let mut accounts = swap_back_accounts(&program_id, U256::one(), &recipient, 50_000);
+        Bridge::unpack::<Bridge>(&mut accounts[5].data)
+            .unwrap()
+            .config
+            .relayer_refund = 5_000;
+        accounts[5].lamports = Bridge::MIN_BRIDGE_BALANCE + 8_000;
+        let bridge_key = accounts[5].key;
+        for nonce in 2..=3 {
+            let receipt_key = Bridge::derive_swap_back_receipt_id(
+                &program_id,
+                &bridge_key,
+                CHAIN_ID,
+                U256::from(nonce),
+            )
+            .unwrap();
+            accounts.push(TestAccount::new(receipt_key, program_id, 0, vec![]));
+        }
+        let mut infos = infos(&mut accounts);
+        let receipt_rent = Rent::default().minimum_balance(size_of::<SwapBackReceipt>());
+        let relayer_paid = |swaps: u64| 1_000_000_000 - swaps * receipt_rent;
+
+        assert_eq!(swap_back(&program_id, &infos, U256::one(), 1_000), Ok(()));
+        assert_eq!(infos[5].lamports(), Bridge::MIN_BRIDGE_BALANCE + 3_000);
+        assert_eq!(infos[13].lamports(), relayer_paid(1) + 5_000);
+
+        // The swap is still paid out when the bridge can no longer afford the refund
+        infos.swap(7, 14);
+        assert_eq!(swap_back(&program_id, &infos, U256::from(2), 1_000), Ok(()));
+        assert_eq!(infos[5].lamports(), Bridge::MIN_BRIDGE_BALANCE + 3_000);
+        assert_eq!(infos[13].lamports(), relayer_paid(2) + 5_000);
+
+        // Exactly the minimum balance is left after a refund
+        **infos[5].lamports.borrow_mut() += 2_000;
+        infos.swap(7, 15);
+        assert_eq!(swap_back(&program_id, &infos, U256::from(3), 1_000), Ok(()));
+        assert_eq!(infos[5].lamports(), Bridge::MIN_BRIDGE_BALANCE);
+        assert_eq!(infos[13].lamports(), relayer_paid(3) + 10_000);
+        assert_eq!(token_balance(&infos[11]), 3_000);
     }
 }
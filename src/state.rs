@@ -1,32 +1,1333 @@
 //! Bridge transition types
 
-use std::{collections::HashMap};
+use std::mem::size_of;
 
 use primitive_types::U256;
-use solana_program::{pubkey::Pubkey};
+use solana_program::{
+    account_info::AccountInfo, instruction::AccountMeta, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey,
+};
+
+use crate::{
+    amount::DecimalMapping,
+    error::Error,
+    instruction::{
+        MAX_FEE_BENEFICIARIES, MAX_MULTISIG_SIGNERS, MAX_PROPOSAL_ACCOUNTS, MAX_PROPOSAL_DATA,
+    },
+};
 
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[allow(non_snake_case)]
 pub struct BridgeConfig {
     pub croge_program: Pubkey,
     pub system: Pubkey,
     pub governor: Pubkey,
     pub bridgeFeesAddress: Pubkey,
     pub owner: Pubkey,
-    pub _bridgeFee: u32,
+    pub bridge_fee: BridgeFee,
+    /// lamports that need to be paid to the bridge for a swap, unless overridden by the chain
+    pub processed_fee: u64,
+    /// seconds a queued config change has to wait before it can be executed
+    pub timelock_delay: u32,
+    /// key that may pause swaps but not resume them, the default key if there is none
+    pub pauser: Pubkey,
+    /// inbound swaps above this amount of the mint are paid out after `withdrawal_delay`, zero
+    /// means never
+    pub large_withdrawal_threshold: U256,
+    /// seconds a large inbound swap is held back
+    pub withdrawal_delay: u32,
+    /// lamports `WithdrawFees` leaves in the bridge on top of its rent exemption, to pay refunds
+    pub fee_floor: u64,
+    /// split of the token fees collected in the fee custody account
+    pub fee_split: FeeSplit,
+    /// lamports refunded from the bridge to the relayer of every inbound swap
+    pub relayer_refund: u64,
  }
 
+impl BridgeConfig {
+    /// Applies a queued change
+    pub fn apply(&mut self, change: ConfigChange, pending: &PendingChange) {
+        match change {
+            ConfigChange::System => self.system = pending.new_key,
+            ConfigChange::CrogeProgram => self.croge_program = pending.new_key,
+            ConfigChange::BridgeFeesAddress => self.bridgeFeesAddress = pending.new_key,
+            ConfigChange::TimelockDelay => self.timelock_delay = pending.new_delay,
+        }
+    }
+}
+
+/// Token account that receives a share of the fees.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeBeneficiary {
+    /// token account of the beneficiary
+    pub account: Pubkey,
+    /// share of the fees, relative to the other weights
+    pub weight: u16,
+}
+
+/// Split of the token fees between the treasury `bridgeFeesAddress` and other beneficiaries.
+/// Without any weights everything goes to the treasury.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeSplit {
+    /// share of the treasury, relative to the weights of the beneficiaries
+    pub treasury_weight: u16,
+    /// beneficiaries next to the treasury
+    pub beneficiaries: [FeeBeneficiary; MAX_FEE_BENEFICIARIES],
+    /// number of beneficiaries
+    pub len_beneficiaries: u8,
+}
+
+impl FeeSplit {
+    /// Splits `balance` by weight. Every beneficiary gets its share rounded down, the treasury
+    /// gets its own share plus the rounding remainder. Returns the shares of the beneficiaries
+    /// and of the treasury.
+    pub fn shares(&self, balance: u64) -> ([u64; MAX_FEE_BENEFICIARIES], u64) {
+        let beneficiaries = &self.beneficiaries[..self.len_beneficiaries as usize];
+        let total_weight = beneficiaries
+            .iter()
+            .fold(self.treasury_weight as u128, |sum, b| sum + b.weight as u128);
+
+        let mut shares = [0u64; MAX_FEE_BENEFICIARIES];
+        if total_weight == 0 {
+            return (shares, balance);
+        }
+
+        let mut distributed = 0u64;
+        for (share, beneficiary) in shares.iter_mut().zip(beneficiaries.iter()) {
+            // Never more than `balance` as the weight is at most the total weight
+            *share = (balance as u128 * beneficiary.weight as u128 / total_weight) as u64;
+            distributed += *share;
+        }
+
+        (shares, balance - distributed)
+    }
+}
+
+/// Config values that can only be changed through the timelock.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConfigChange {
+    /// `BridgeConfig.system`, set by `SetSystem`
+    System = 0,
+    /// `BridgeConfig.croge_program`, set by `UpdateCrogeProgram`
+    CrogeProgram = 1,
+    /// `BridgeConfig.bridgeFeesAddress`, set by `SetBridgeFeesAddress`
+    BridgeFeesAddress = 2,
+    /// `BridgeConfig.timelock_delay`, set by `SetTimelockDelay`
+    TimelockDelay = 3,
+}
+
+/// Queued change of a config value, derived from the bridge and the changed value. There is at
+/// most one pending change per value. The account is closed when the change is executed or
+/// cancelled.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingChange {
+    /// new value of a key
+    pub new_key: Pubkey,
+    /// new value of `timelock_delay`
+    pub new_delay: u32,
+    /// unix timestamp from which the change can be executed
+    pub eta: i64,
+    /// account that paid for the pending change and receives the rent back
+    pub payer: Pubkey,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl PendingChange {
+    /// Delay used until the governor changes it, in seconds
+    pub const DEFAULT_DELAY: u32 = 2 * 24 * 60 * 60;
+}
+
+/// Fee the bridge keeps from inbound swaps.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BridgeFee {
+    /// proportional fee in basis points of the amount
+    pub bps: u16,
+    /// lower bound of the fee
+    pub min_fee: U256,
+    /// upper bound of the fee, zero means no upper bound
+    pub max_fee: U256,
+}
+
+impl BridgeFee {
+    /// Basis points of a whole amount
+    pub const BPS_DENOMINATOR: u16 = 10000;
+
+    /// Calculates the fee for a swap of `amount`.
+    ///
+    /// The proportional part is `amount * bps / 10000` rounded down, so a user is never charged
+    /// more than the configured rate. It is then raised to `min_fee`, lowered to `max_fee` and
+    /// finally capped at `amount` itself.
+    pub fn calculate(&self, amount: U256) -> Result<U256, Error> {
+        let bps = U256::from(self.bps);
+        let denominator = U256::from(Self::BPS_DENOMINATOR);
+
+        // floor(amount * bps / d) == (amount / d) * bps + floor((amount % d) * bps / d)
+        // which cannot overflow for amounts close to U256::MAX
+        let fee = (amount / denominator)
+            .checked_mul(bps)
+            .and_then(|fee| fee.checked_add((amount % denominator).checked_mul(bps)? / denominator))
+            .ok_or(Error::InvalidFee)?;
+
+        let mut fee = fee.max(self.min_fee);
+        if !self.max_fee.is_zero() {
+            fee = fee.min(self.max_fee);
+        }
+        Ok(fee.min(amount))
+    }
+
+    /// Checks that the fee parameters are consistent
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.bps > Self::BPS_DENOMINATOR {
+            return Err(Error::InvalidFee);
+        }
+        if !self.max_fee.is_zero() && self.min_fee > self.max_fee {
+            return Err(Error::InvalidFee);
+        }
+        Ok(())
+    }
+}
+
 /// Bridge state.
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[allow(non_snake_case)]
 pub struct Bridge {
-    pub _nonces: HashMap<U256, U256>,
-    pub nonceProcessed: HashMap<u32, HashMap<U256, bool>>,
-    pub _processedFees: HashMap<u32, U256>,
-    pub _isExcludedFromFees: HashMap<Pubkey, bool>,
     pub _isBridgingPaused: bool,
 
     pub config: BridgeConfig,
+
+    /// owner change waiting to be accepted by the new owner
+    pub pending_owner: PendingRole,
+    /// governor change waiting to be accepted by the new governor
+    pub pending_governor: PendingRole,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl Bridge {
+    /// Key currently holding a role
+    pub fn role_key_mut(&mut self, role: Role) -> &mut Pubkey {
+        match role {
+            Role::Owner => &mut self.config.owner,
+            Role::Governor => &mut self.config.governor,
+        }
+    }
+
+    /// Pending change of a role
+    pub fn pending_role_mut(&mut self, role: Role) -> &mut PendingRole {
+        match role {
+            Role::Owner => &mut self.pending_owner,
+            Role::Governor => &mut self.pending_governor,
+        }
+    }
+}
+
+/// Roles that are handed over in two steps, proposed by the owner and accepted by the new key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Owner,
+    Governor,
+}
+
+/// Proposed holder of a role.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingRole {
+    /// key that has to sign the accept
+    pub key: Pubkey,
+    /// unix timestamp after which the proposal can no longer be accepted
+    pub expiry: i64,
+    /// Is `true` if a change is waiting to be accepted
+    pub is_pending: bool,
+}
+
+impl PendingRole {
+    /// Seconds a proposed role change can be accepted for
+    pub const EXPIRY: i64 = 7 * 24 * 60 * 60;
+}
+
+/// State of a destination chain, derived from the bridge and the chain id.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChainState {
+    /// id of the chain
+    pub chain_id: u32,
+    /// nonce of the latest outbound swap to this chain
+    pub nonce: U256,
+    /// fee overrides for swaps to this chain
+    pub outbound_fee: ChainFee,
+    /// fee overrides for swaps from this chain
+    pub inbound_fee: ChainFee,
+    /// swaps with this chain are only accepted if it is enabled
+    pub is_enabled: bool,
+    /// swaps to this chain are paused
+    pub is_outbound_paused: bool,
+    /// swaps from this chain are paused
+    pub is_inbound_paused: bool,
+    /// volume limit of swaps to this chain
+    pub outbound_limit: RateLimit,
+    /// volume limit of swaps from this chain
+    pub inbound_limit: RateLimit,
+    /// pauses swaps from this chain when they look suspicious
+    pub inbound_breaker: CircuitBreaker,
+    /// smallest amount of a swap to this chain, zero means no minimum
+    pub min_amount: U256,
+    /// largest amount of a swap to this chain, zero means no maximum
+    pub max_amount: U256,
+    /// format of recipient addresses on this chain
+    pub address_format: AddressFormat,
+    /// decimals of the token on this chain and of the mint
+    pub decimals: DecimalMapping,
+    /// dust truncated from swaps from this chain, in its decimals, not yet paid out as fees
+    pub inbound_dust: U256,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl ChainState {
+    /// Fee overrides of the given direction
+    pub fn fee(&self, direction: SwapDirection) -> &ChainFee {
+        match direction {
+            SwapDirection::Outbound => &self.outbound_fee,
+            SwapDirection::Inbound => &self.inbound_fee,
+        }
+    }
+
+    /// Mutable fee overrides of the given direction
+    pub fn fee_mut(&mut self, direction: SwapDirection) -> &mut ChainFee {
+        match direction {
+            SwapDirection::Outbound => &mut self.outbound_fee,
+            SwapDirection::Inbound => &mut self.inbound_fee,
+        }
+    }
+
+    /// Mutable volume limit of the given direction
+    pub fn limit_mut(&mut self, direction: SwapDirection) -> &mut RateLimit {
+        match direction {
+            SwapDirection::Outbound => &mut self.outbound_limit,
+            SwapDirection::Inbound => &mut self.inbound_limit,
+        }
+    }
+
+    /// Mutable pause flag of the given direction
+    pub fn paused_mut(&mut self, direction: SwapDirection) -> &mut bool {
+        match direction {
+            SwapDirection::Outbound => &mut self.is_outbound_paused,
+            SwapDirection::Inbound => &mut self.is_inbound_paused,
+        }
+    }
+
+    /// Checks that the amount of a swap to this chain is within its bounds
+    pub fn check_amount(&self, amount: U256) -> Result<(), Error> {
+        if amount < self.min_amount {
+            return Err(Error::AmountTooSmall);
+        }
+        if !self.max_amount.is_zero() && amount > self.max_amount {
+            return Err(Error::AmountTooLarge);
+        }
+        Ok(())
+    }
+}
+
+/// Number of buckets a rate limit window is split into
+pub const RATE_LIMIT_BUCKETS: usize = 24;
+
+/// Maximum amount that may be swapped within a rolling window. The window is split into
+/// `RATE_LIMIT_BUCKETS` buckets, a bucket leaves the window as a whole once it is older than
+/// `window`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RateLimit {
+    /// maximum amount per window, zero means no limit
+    pub max_amount: U256,
+    /// length of the window in seconds
+    pub window: u32,
+    /// amount swapped in each bucket
+    pub buckets: [U256; RATE_LIMIT_BUCKETS],
+    /// number of the latest bucket that was written, counted from the unix epoch
+    pub last_bucket: i64,
+}
+
+impl RateLimit {
+    /// Length of a bucket in seconds
+    pub fn bucket_len(&self) -> i64 {
+        (self.window as i64 / RATE_LIMIT_BUCKETS as i64).max(1)
+    }
+
+    /// Clears the buckets that left the window ending at `now`
+    fn advance(&mut self, now: i64) {
+        let current = now.div_euclid(self.bucket_len());
+        let elapsed = current - self.last_bucket;
+        if elapsed <= 0 {
+            return;
+        }
+
+        for i in 1..=elapsed.min(RATE_LIMIT_BUCKETS as i64) {
+            let bucket = (self.last_bucket + i).rem_euclid(RATE_LIMIT_BUCKETS as i64);
+            self.buckets[bucket as usize] = U256::zero();
+        }
+        self.last_bucket = current;
+    }
+
+    /// Amount swapped within the window ending at `now`
+    pub fn used(&self, now: i64) -> U256 {
+        let mut limit = *self;
+        limit.advance(now);
+        limit
+            .buckets
+            .iter()
+            .fold(U256::zero(), |sum, amount| sum.saturating_add(*amount))
+    }
+
+    /// Records a swap of `amount` at `now`. Fails if it would exceed the limit.
+    pub fn consume(&mut self, now: i64, amount: U256) -> Result<(), Error> {
+        if self.max_amount.is_zero() {
+            return Ok(());
+        }
+
+        let total = self
+            .used(now)
+            .checked_add(amount)
+            .ok_or(Error::RateLimitExceeded)?;
+        if total > self.max_amount {
+            return Err(Error::RateLimitExceeded);
+        }
+
+        self.record(now, amount);
+        Ok(())
+    }
+
+    /// Records a swap of `amount` at `now` even if it exceeds the limit
+    pub fn record(&mut self, now: i64, amount: U256) {
+        if self.max_amount.is_zero() {
+            return;
+        }
+
+        self.advance(now);
+        let bucket = self.last_bucket.rem_euclid(RATE_LIMIT_BUCKETS as i64) as usize;
+        self.buckets[bucket] = self.buckets[bucket].saturating_add(amount);
+    }
+}
+
+/// Pauses inbound swaps of a chain on its own when a swap looks suspicious. The thresholds are
+/// meant to be lower than the rate limit, the governor has to review and resume the chain. Once
+/// the governor approved the swap that tripped the breaker it can be resubmitted and passes it
+/// once.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CircuitBreaker {
+    /// amount of a single swap that trips the breaker, zero means no threshold
+    pub max_amount: U256,
+    /// volume per hour that trips the breaker, a `max_amount` of zero means no threshold
+    pub hourly_volume: RateLimit,
+    /// nonce of the swap that tripped the breaker last
+    pub tripped_nonce: U256,
+    /// time the breaker tripped last
+    pub tripped_time: i64,
+    /// the swap of `tripped_nonce` has not passed the breaker yet
+    pub is_tripped: bool,
+    /// the governor approved the swap of `tripped_nonce`
+    pub is_approved: bool,
+}
+
+impl CircuitBreaker {
+    /// Window of `hourly_volume` in seconds
+    pub const HOUR: u32 = 60 * 60;
+
+    /// Records a swap of `amount` at `now`. Returns `true` if the swap trips the breaker, in
+    /// which case it is not recorded and the breaker remembers its nonce. Once approved, the swap
+    /// that tripped the breaker last passes once without being checked, but it is recorded.
+    pub fn trips(&mut self, now: i64, nonce: U256, amount: U256) -> bool {
+        if self.is_tripped && self.is_approved && nonce == self.tripped_nonce {
+            self.is_tripped = false;
+            self.is_approved = false;
+            self.hourly_volume.record(now, amount);
+            return false;
+        }
+
+        let trips = (!self.max_amount.is_zero() && amount > self.max_amount)
+            || self.hourly_volume.consume(now, amount).is_err();
+        if trips {
+            self.tripped_nonce = nonce;
+            self.tripped_time = now;
+            self.is_tripped = true;
+            self.is_approved = false;
+        }
+        trips
+    }
+}
+
+/// Direction of a swap, seen from Solana.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SwapDirection {
+    /// `Swap`, from Solana to a foreign chain
+    Outbound = 0,
+    /// `SwapBack`, from a foreign chain to Solana
+    Inbound = 1,
+}
+
+/// Format of wallet addresses on a foreign chain. Recipients are always passed as 32 bytes.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AddressFormat {
+    /// any 32 byte address
+    #[default]
+    Bytes32 = 0,
+    /// 20 byte EVM address, left padded with zeros
+    Evm = 1,
+}
+
+impl AddressFormat {
+    /// Checks that `recipient` is a well formed, non-zero address of this format
+    pub fn validate(&self, recipient: &[u8; 32]) -> Result<(), Error> {
+        let padding = match self {
+            AddressFormat::Bytes32 => 0,
+            AddressFormat::Evm => 12,
+        };
+        if recipient[..padding].iter().any(|b| *b != 0) {
+            return Err(Error::InvalidRecipient);
+        }
+        if recipient[padding..].iter().all(|b| *b == 0) {
+            return Err(Error::InvalidRecipient);
+        }
+        Ok(())
+    }
+}
+
+/// Fee overrides of a chain for one swap direction. Values that are not set fall back to the
+/// ones in the bridge config.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChainFee {
+    /// basis points used instead of `BridgeConfig.bridge_fee.bps` if `has_bps` is set
+    pub bps: u16,
+    /// `bps` overrides the config, otherwise it is ignored
+    pub has_bps: bool,
+    /// lamports used instead of `BridgeConfig.processed_fee` if `has_processed_fee` is set
+    pub processed_fee: u64,
+    /// `processed_fee` overrides the config, otherwise it is ignored
+    pub has_processed_fee: bool,
+}
+
+impl ChainFee {
+    /// The fee the bridge keeps from a swap. The min and max fee always come from the config.
+    pub fn resolve_bridge_fee(&self, config: &BridgeConfig) -> BridgeFee {
+        if self.has_bps {
+            BridgeFee {
+                bps: self.bps,
+                ..config.bridge_fee
+            }
+        } else {
+            config.bridge_fee
+        }
+    }
+
+    /// The lamports that need to be paid to the bridge for a swap
+    pub fn resolve_processed_fee(&self, config: &BridgeConfig) -> u64 {
+        if self.has_processed_fee {
+            self.processed_fee
+        } else {
+            config.processed_fee
+        }
+    }
+}
+
+/// Record of an outbound swap, derived from the bridge, the destination chain and the nonce.
+/// The relayer reads it to release the tokens on the destination chain.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SwapRecord {
+    /// chain the tokens are sent to
+    pub to_chain_id: u32,
+    /// nonce of the swap for the destination chain
+    pub nonce: U256,
+    /// wallet that sent the tokens
+    pub sender: Pubkey,
+    /// address that receives the tokens on the destination chain
+    pub recipient: [u8; 32],
+    /// amount of tokens released on the destination chain, in its decimals
+    pub amount: U256,
+    /// time the tokens were locked
+    pub lockup_time: u32,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+/// Idempotency key of an outbound swap, derived from the bridge, the sender and the key. Its
+/// existence is what prevents a resubmitted swap from being processed twice.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SwapIdempotency {
+    /// wallet that sent the swap
+    pub sender: Pubkey,
+    /// key chosen by the sender
+    pub key: u64,
+    /// chain of the swap that used the key
+    pub to_chain_id: u32,
+    /// nonce of the swap that used the key
+    pub nonce: U256,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+/// Receipt of a processed inbound swap, derived from the bridge, the source chain and the nonce.
+/// Its existence is what prevents a swap from being paid out twice.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SwapBackReceipt {
+    /// chain the swap came from
+    pub from_chain_id: u32,
+    /// nonce of the swap on the source chain
+    pub nonce: U256,
+    /// time the swap was processed
+    pub processed_time: u32,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+/// Large inbound swap waiting to be paid out, derived from the bridge, the source chain and the
+/// nonce. The account is closed when the swap is claimed or cancelled.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PendingWithdrawal {
+    /// chain the swap came from
+    pub from_chain_id: u32,
+    /// nonce of the swap on the source chain
+    pub nonce: U256,
+    /// token account that receives the swap
+    pub to: Pubkey,
+    /// amount paid to `to`
+    pub amount: U256,
+    /// amount paid to `bridgeFeesAddress`
+    pub fee: U256,
+    /// unix timestamp from which the swap can be claimed
+    pub release_time: i64,
+    /// account that paid for the pending withdrawal and receives the rent back
+    pub payer: Pubkey,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+/// Marks a wallet as excluded from the bridge fee, derived from the bridge and the wallet.
+/// The account is closed when the exemption is removed.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeeExemption {
+    /// wallet that is excluded from fees
+    pub wallet: Pubkey,
+    /// governor that added the exemption
+    pub added_by: Pubkey,
+    /// time the exemption was added
+    pub added_time: u32,
+    /// account that paid the rent of the exemption and gets it back when it is removed
+    pub payer: Pubkey,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl FeeExemption {
+    /// Size of the account, usable as a data size filter when listing all exemptions
+    pub const LEN: usize = size_of::<FeeExemption>();
+    /// Offset of `wallet` in the account data, usable as a memcmp filter
+    pub const WALLET_OFFSET: usize = 0;
+    /// Offset of `added_by` in the account data, usable as a memcmp filter
+    pub const ADDED_BY_OFFSET: usize = 32;
+}
+
+/// Signers of the multisig governor, derived from the bridge. The multisig only has power over
+/// the bridge once its address has been made the governor.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Multisig {
+    /// keys that may propose and approve admin actions, only the first `len_signers` are used
+    pub signers: [Pubkey; MAX_MULTISIG_SIGNERS],
+    /// number of signers
+    pub len_signers: u8,
+    /// number of approvals a proposal needs to be executed
+    pub threshold: u8,
+    /// index of the next proposal
+    pub proposal_count: u64,
+    /// incremented whenever the signers change, which invalidates all open proposals
+    pub version: u64,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl Multisig {
+    /// Position of a key in `signers`
+    pub fn signer_index(&self, key: &Pubkey) -> Option<usize> {
+        self.signers[..self.len_signers as usize]
+            .iter()
+            .position(|signer| signer == key)
+    }
+}
+
+/// Admin instruction waiting for the approval of the multisig, derived from the bridge and the
+/// index of the proposal.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Proposal {
+    /// index of the proposal
+    pub index: u64,
+    /// version of the multisig the proposal was created for
+    pub multisig_version: u64,
+    /// signer that created the proposal
+    pub proposer: Pubkey,
+    /// approvals by the signer at the same position in `Multisig.signers`
+    pub approvals: [bool; MAX_MULTISIG_SIGNERS],
+    /// serialized `BridgeInstruction`, only the first `data_len` bytes are used
+    pub data: [u8; MAX_PROPOSAL_DATA],
+    /// length of the serialized instruction
+    pub data_len: u16,
+    /// accounts of the instruction, only the first `accounts_len` are used
+    pub accounts: [ProposalAccount; MAX_PROPOSAL_ACCOUNTS],
+    /// number of accounts of the instruction
+    pub accounts_len: u8,
+    /// proposals can only be executed once
+    pub is_executed: bool,
+
+    /// Is `true` if this structure has been initialized.
+    pub is_initialized: bool,
+}
+
+impl Proposal {
+    /// Number of signers that approved the proposal
+    pub fn approval_count(&self) -> usize {
+        self.approvals.iter().filter(|approved| **approved).count()
+    }
+
+    /// Accounts of the proposed instruction
+    pub fn account_metas(&self) -> Vec<AccountMeta> {
+        self.accounts[..self.accounts_len as usize]
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.pubkey,
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            })
+            .collect()
+    }
+}
+
+/// Account of an instruction proposed to the multisig
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProposalAccount {
+    /// key of the account
+    pub pubkey: Pubkey,
+    /// the account signs the instruction, the multisig signs when the proposal is executed
+    pub is_signer: bool,
+    /// the account is writable
+    pub is_writable: bool,
 }
 
+impl From<&AccountMeta> for ProposalAccount {
+    fn from(meta: &AccountMeta) -> Self {
+        ProposalAccount {
+            pubkey: meta.pubkey,
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+/// Implementation of serialization functions
+impl Bridge {
+    /// Deserializes a spl_token `Account`.
+    pub fn token_account_deserialize(
+        info: &AccountInfo,
+    ) -> Result<spl_token::state::Account, Error> {
+        spl_token::state::Account::unpack(&info.data.borrow()).map_err(|_| Error::ExpectedAccount)
+    }
+
+    /// Deserializes a spl_token `Mint`.
+    pub fn mint_deserialize(info: &AccountInfo) -> Result<spl_token::state::Mint, Error> {
+        spl_token::state::Mint::unpack(&info.data.borrow()).map_err(|_| Error::ExpectedToken)
+    }
+
+    /// Unpacks a state from a bytes buffer while assuring that the state is initialized.
+    pub fn unpack<T: IsInitialized>(input: &mut [u8]) -> Result<&mut T, ProgramError> {
+        let mut_ref: &mut T = Self::unpack_unchecked(input)?;
+        if !mut_ref.is_initialized() {
+            return Err(Error::UninitializedState.into());
+        }
+        Ok(mut_ref)
+    }
+
+    /// Unpacks a state from a bytes buffer without checking that the state is initialized.
+    pub fn unpack_unchecked<T: IsInitialized>(input: &mut [u8]) -> Result<&mut T, ProgramError> {
+        if input.len() != size_of::<T>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        #[allow(clippy::cast_ptr_alignment)]
+        Ok(unsafe { &mut *(&mut input[0] as *mut u8 as *mut T) })
+    }
+
+    /// Unpacks a state from a bytes buffer while assuring that the state is initialized.
+    pub fn unpack_immutable<T: IsInitialized>(input: &[u8]) -> Result<&T, ProgramError> {
+        let immutable_ref: &T = Self::unpack_unchecked_immutable(input)?;
+        if !immutable_ref.is_initialized() {
+            return Err(Error::UninitializedState.into());
+        }
+        Ok(immutable_ref)
+    }
+
+    /// Unpacks a state from a bytes buffer without checking that the state is initialized.
+    pub fn unpack_unchecked_immutable<T: IsInitialized>(input: &[u8]) -> Result<&T, ProgramError> {
+        if input.len() != size_of::<T>() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        #[allow(clippy::cast_ptr_alignment)]
+        Ok(unsafe { &*(&input[0] as *const u8 as *const T) })
+    }
+}
+
+/// Implementation of derivations
+impl Bridge {
+    /// Calculates derived seeds for a bridge
+    pub fn derive_bridge_seeds() -> Vec<Vec<u8>> {
+        vec!["bridge".as_bytes().to_vec()]
+    }
+
+    /// Calculates derived seeds for a custody account
+    pub fn derive_custody_seeds(bridge: &Pubkey, mint: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            "custody".as_bytes().to_vec(),
+            bridge.to_bytes().to_vec(),
+            mint.to_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for this program
+    pub fn derive_bridge_id(program_id: &Pubkey) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_bridge_seeds())?.0)
+    }
+
+    /// Calculates a derived address for a custody account
+    pub fn derive_custody_id(
+        program_id: &Pubkey,
+        bridge: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_custody_seeds(bridge, mint))?.0)
+    }
+
+    /// Calculates a derived address for the given seeds, together with the seeds including the
+    /// bump seed that are needed to sign for it
+    pub fn derive_key(
+        program_id: &Pubkey,
+        seeds: &[Vec<u8>],
+    ) -> Result<(Pubkey, Vec<Vec<u8>>), Error> {
+        Ok(Self::find_program_address(seeds, program_id))
+    }
+
+    /// Finds a valid program address, together with the seeds including the bump seed
+    pub fn find_program_address(seeds: &[Vec<u8>], program_id: &Pubkey) -> (Pubkey, Vec<Vec<u8>>) {
+        let s: Vec<_> = seeds.iter().map(|item| item.as_slice()).collect();
+        let (key, bump_seed) = Pubkey::find_program_address(&s, program_id);
+        let mut full_seeds = seeds.to_vec();
+        full_seeds.push(vec![bump_seed]);
+        (key, full_seeds)
+    }
+
+    /// Calculates derived seeds for the pending change of a config value
+    pub fn derive_pending_change_seeds(bridge_key: &Pubkey, change: ConfigChange) -> Vec<Vec<u8>> {
+        vec![
+            "pending_change".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            vec![change as u8],
+        ]
+    }
+
+    /// Calculates a derived address for the pending change of a config value
+    pub fn derive_pending_change_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        change: ConfigChange,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_pending_change_seeds(bridge_key, change))?.0)
+    }
+
+    /// Calculates derived seeds for the multisig governor
+    pub fn derive_multisig_seeds(bridge_key: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            "multisig".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the multisig governor
+    pub fn derive_multisig_id(program_id: &Pubkey, bridge_key: &Pubkey) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_multisig_seeds(bridge_key))?.0)
+    }
+
+    /// Calculates derived seeds for a proposal of the multisig governor
+    pub fn derive_proposal_seeds(bridge_key: &Pubkey, index: u64) -> Vec<Vec<u8>> {
+        vec![
+            "proposal".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            index.to_be_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for a proposal of the multisig governor
+    pub fn derive_proposal_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        index: u64,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_proposal_seeds(bridge_key, index))?.0)
+    }
+
+    /// Calculates derived seeds for the state of a chain
+    pub fn derive_chain_state_seeds(bridge_key: &Pubkey, chain_id: u32) -> Vec<Vec<u8>> {
+        vec![
+            "chain".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            chain_id.to_be_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the state of a chain
+    pub fn derive_chain_state_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        chain_id: u32,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_chain_state_seeds(bridge_key, chain_id))?.0)
+    }
+
+    /// Calculates derived seeds for the record of an outbound swap
+    pub fn derive_swap_record_seeds(bridge_key: &Pubkey, to_chain_id: u32, nonce: U256) -> Vec<Vec<u8>> {
+        let mut nonce_bytes = [0u8; 32];
+        nonce.to_big_endian(&mut nonce_bytes);
+        vec![
+            "swap".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            to_chain_id.to_be_bytes().to_vec(),
+            nonce_bytes.to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the record of an outbound swap
+    pub fn derive_swap_record_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        to_chain_id: u32,
+        nonce: U256,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_swap_record_seeds(bridge_key, to_chain_id, nonce))?.0)
+    }
+
+    /// Calculates derived seeds for the idempotency key of an outbound swap
+    pub fn derive_swap_idempotency_seeds(
+        bridge_key: &Pubkey,
+        sender: &Pubkey,
+        key: u64,
+    ) -> Vec<Vec<u8>> {
+        vec![
+            "idempotency".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            sender.to_bytes().to_vec(),
+            key.to_be_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the idempotency key of an outbound swap
+    pub fn derive_swap_idempotency_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        sender: &Pubkey,
+        key: u64,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(
+            program_id,
+            &Self::derive_swap_idempotency_seeds(bridge_key, sender, key),
+        )?
+        .0)
+    }
+
+    /// Calculates derived seeds for the receipt of an inbound swap
+    pub fn derive_swap_back_receipt_seeds(
+        bridge_key: &Pubkey,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> Vec<Vec<u8>> {
+        let mut nonce_bytes = [0u8; 32];
+        nonce.to_big_endian(&mut nonce_bytes);
+        vec![
+            "receipt".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            from_chain_id.to_be_bytes().to_vec(),
+            nonce_bytes.to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the receipt of an inbound swap
+    pub fn derive_swap_back_receipt_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(
+            program_id,
+            &Self::derive_swap_back_receipt_seeds(bridge_key, from_chain_id, nonce),
+        )?
+        .0)
+    }
+
+    /// Calculates derived seeds for a delayed inbound swap
+    pub fn derive_pending_withdrawal_seeds(
+        bridge_key: &Pubkey,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> Vec<Vec<u8>> {
+        let mut nonce_bytes = [0u8; 32];
+        nonce.to_big_endian(&mut nonce_bytes);
+        vec![
+            "withdrawal".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            from_chain_id.to_be_bytes().to_vec(),
+            nonce_bytes.to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for a delayed inbound swap
+    pub fn derive_pending_withdrawal_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        from_chain_id: u32,
+        nonce: U256,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(
+            program_id,
+            &Self::derive_pending_withdrawal_seeds(bridge_key, from_chain_id, nonce),
+        )?
+        .0)
+    }
+
+    /// Calculates derived seeds for the account the token fees of a mint accrue in
+    pub fn derive_fee_custody_seeds(bridge_key: &Pubkey, mint: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            "fee_custody".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            mint.to_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the account the token fees of a mint accrue in
+    pub fn derive_fee_custody_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        mint: &Pubkey,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_fee_custody_seeds(bridge_key, mint))?.0)
+    }
+
+    /// Calculates derived seeds for the fee exemption of a wallet
+    pub fn derive_fee_exemption_seeds(bridge_key: &Pubkey, wallet: &Pubkey) -> Vec<Vec<u8>> {
+        vec![
+            "fee_exemption".as_bytes().to_vec(),
+            bridge_key.to_bytes().to_vec(),
+            wallet.to_bytes().to_vec(),
+        ]
+    }
+
+    /// Calculates a derived address for the fee exemption of a wallet
+    pub fn derive_fee_exemption_id(
+        program_id: &Pubkey,
+        bridge_key: &Pubkey,
+        wallet: &Pubkey,
+    ) -> Result<Pubkey, Error> {
+        Ok(Self::derive_key(program_id, &Self::derive_fee_exemption_seeds(bridge_key, wallet))?.0)
+    }
+}
+
+/// Check is a token state is initialized
+pub trait IsInitialized {
+    /// Is initialized
+    fn is_initialized(&self) -> bool;
+}
+
+impl IsInitialized for Bridge {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for ChainState {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for SwapBackReceipt {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for FeeExemption {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for SwapRecord {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for Multisig {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for Proposal {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for PendingChange {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for PendingWithdrawal {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl IsInitialized for SwapIdempotency {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bridge_fee(bps: u16, min_fee: u64, max_fee: u64) -> BridgeFee {
+        BridgeFee {
+            bps,
+            min_fee: U256::from(min_fee),
+            max_fee: U256::from(max_fee),
+        }
+    }
+
+    #[test]
+    fn bridge_fee_rounds_down() {
+        let fee = bridge_fee(30, 0, 0);
+        assert_eq!(fee.calculate(U256::from(3333)), Ok(U256::from(9)));
+        assert_eq!(fee.calculate(U256::from(3334)), Ok(U256::from(10)));
+
+        let fee = bridge_fee(1, 0, 0);
+        assert_eq!(fee.calculate(U256::from(9999)), Ok(U256::zero()));
+        assert_eq!(fee.calculate(U256::from(10000)), Ok(U256::from(1)));
+        assert_eq!(fee.calculate(U256::from(19999)), Ok(U256::from(1)));
+    }
+
+    #[test]
+    fn bridge_fee_does_not_overflow() {
+        let max = U256::max_value();
+        assert_eq!(bridge_fee(10000, 0, 0).calculate(max), Ok(max));
+        assert_eq!(bridge_fee(5000, 0, 0).calculate(max), Ok(max / 2));
+        assert_eq!(bridge_fee(0, 0, 0).calculate(max), Ok(U256::zero()));
+    }
+
+    #[test]
+    fn bridge_fee_applies_bounds() {
+        let fee = bridge_fee(100, 50, 500);
+        assert_eq!(fee.calculate(U256::from(1000)), Ok(U256::from(50)));
+        assert_eq!(fee.calculate(U256::from(20000)), Ok(U256::from(200)));
+        assert_eq!(fee.calculate(U256::from(1_000_000)), Ok(U256::from(500)));
+    }
+
+    #[test]
+    fn bridge_fee_is_capped_at_the_amount() {
+        let fee = bridge_fee(100, 50, 0);
+        assert_eq!(fee.calculate(U256::from(30)), Ok(U256::from(30)));
+        assert_eq!(fee.calculate(U256::zero()), Ok(U256::zero()));
+        assert_eq!(bridge_fee(10000, 0, 0).calculate(U256::from(7)), Ok(U256::from(7)));
+    }
+
+    #[test]
+    fn bridge_fee_validate() {
+        assert_eq!(bridge_fee(10000, 0, 0).validate(), Ok(()));
+        assert_eq!(bridge_fee(10001, 0, 0).validate(), Err(Error::InvalidFee));
+        assert_eq!(bridge_fee(10, 500, 500).validate(), Ok(()));
+        assert_eq!(bridge_fee(10, 501, 500).validate(), Err(Error::InvalidFee));
+        // A zero max_fee means no upper bound
+        assert_eq!(bridge_fee(10, 501, 0).validate(), Ok(()));
+    }
+
+    /// Limit of 100 per 2400 seconds, split into buckets of 100 seconds
+    fn rate_limit() -> RateLimit {
+        RateLimit {
+            max_amount: U256::from(100),
+            window: 2_400,
+            ..RateLimit::default()
+        }
+    }
+
+    #[test]
+    fn rate_limit_allows_exactly_the_limit() {
+        let mut limit = rate_limit();
+        assert_eq!(limit.consume(0, U256::from(60)), Ok(()));
+        assert_eq!(limit.consume(50, U256::from(40)), Ok(()));
+        assert_eq!(limit.used(50), U256::from(100));
+        assert_eq!(limit.consume(150, U256::zero()), Ok(()));
+    }
+
+    #[test]
+    fn rate_limit_rejects_one_over() {
+        let mut limit = rate_limit();
+        assert_eq!(limit.consume(0, U256::from(101)), Err(Error::RateLimitExceeded));
+        assert_eq!(limit.consume(0, U256::from(60)), Ok(()));
+        assert_eq!(limit.consume(1_000, U256::from(41)), Err(Error::RateLimitExceeded));
+        // A rejected swap is not recorded
+        assert_eq!(limit.used(1_000), U256::from(60));
+        assert_eq!(limit.consume(1_000, U256::max_value()), Err(Error::RateLimitExceeded));
+    }
+
+    #[test]
+    fn rate_limit_resets_after_window() {
+        let mut limit = rate_limit();
+        assert_eq!(limit.consume(0, U256::from(100)), Ok(()));
+        assert_eq!(limit.consume(2_399, U256::from(1)), Err(Error::RateLimitExceeded));
+        assert_eq!(limit.consume(2_400, U256::from(100)), Ok(()));
+        assert_eq!(limit.used(2_400), U256::from(100));
+
+        // Long gaps clear every bucket
+        assert_eq!(limit.consume(1_000_000, U256::from(100)), Ok(()));
+        assert_eq!(limit.used(1_000_000), U256::from(100));
+    }
+
+    #[test]
+    fn rate_limit_ignores_time_going_backwards() {
+        let mut limit = rate_limit();
+        assert_eq!(limit.consume(1_000, U256::from(50)), Ok(()));
+        // Earlier timestamps count into the latest bucket and never clear it
+        assert_eq!(limit.consume(500, U256::from(30)), Ok(()));
+        assert_eq!(limit.used(0), U256::from(80));
+        assert_eq!(limit.consume(-5_000, U256::from(21)), Err(Error::RateLimitExceeded));
+        assert_eq!(limit.last_bucket, 10);
+        assert_eq!(limit.consume(3_400, U256::from(100)), Ok(()));
+    }
+
+    #[test]
+    fn rate_limit_without_max_amount_is_unlimited() {
+        let mut limit = RateLimit::default();
+        assert_eq!(limit.consume(0, U256::max_value()), Ok(()));
+        assert_eq!(limit.consume(0, U256::max_value()), Ok(()));
+    }
+
+    #[test]
+    fn approved_swap_passes_breaker_once_and_is_recorded() {
+        let mut breaker = CircuitBreaker {
+            max_amount: U256::from(1_000),
+            hourly_volume: RateLimit {
+                max_amount: U256::from(1_500),
+                window: CircuitBreaker::HOUR,
+                ..RateLimit::default()
+            },
+            ..CircuitBreaker::default()
+        };
+        let nonce = U256::from(7);
+        assert!(!breaker.trips(100, U256::from(6), U256::from(1_000)));
+        assert!(breaker.trips(200, nonce, U256::from(1_001)));
+        assert_eq!(breaker.tripped_nonce, nonce);
+        assert_eq!(breaker.tripped_time, 200);
+
+        // Without approval the resubmitted swap trips the breaker again
+        assert!(breaker.trips(300, nonce, U256::from(1_001)));
+        breaker.is_approved = true;
+        assert!(!breaker.trips(300, nonce, U256::from(1_001)));
+        assert_eq!(breaker.hourly_volume.used(300), U256::from(2_001));
+
+        // It passes only once, other swaps are still checked against the recorded volume
+        assert!(breaker.trips(400, nonce, U256::from(1_001)));
+        assert!(!breaker.is_approved);
+        assert!(breaker.trips(400, U256::from(8), U256::from(1)));
+        assert_eq!(breaker.tripped_nonce, U256::from(8));
+    }
+
+    fn fee_split(treasury_weight: u16, weights: &[u16]) -> FeeSplit {
+        let mut split = FeeSplit {
+            treasury_weight,
+            len_beneficiaries: weights.len() as u8,
+            ..FeeSplit::default()
+        };
+        for (i, weight) in weights.iter().enumerate() {
+            split.beneficiaries[i] = FeeBeneficiary {
+                account: Pubkey::new(&[i as u8 + 1; 32]),
+                weight: *weight,
+            };
+        }
+        split
+    }
+
+    #[test]
+    fn fee_split_without_weights_goes_to_treasury() {
+        assert_eq!(fee_split(0, &[]).shares(1_000), ([0; MAX_FEE_BENEFICIARIES], 1_000));
+        assert_eq!(fee_split(0, &[]).shares(0), ([0; MAX_FEE_BENEFICIARIES], 0));
+    }
+
+    #[test]
+    fn fee_split_without_treasury_weight() {
+        let split = fee_split(0, &[1, 3]);
+        assert_eq!(split.shares(1_000), ([250, 750, 0, 0], 0));
+        // The treasury still gets the rounding remainder
+        assert_eq!(split.shares(1_001), ([250, 750, 0, 0], 1));
+        assert_eq!(split.shares(3), ([0, 2, 0, 0], 1));
+    }
+
+    #[test]
+    fn fee_split_remainder_goes_to_treasury() {
+        let split = fee_split(1, &[1, 1]);
+        assert_eq!(split.shares(10), ([3, 3, 0, 0], 4));
+        assert_eq!(split.shares(2), ([0, 0, 0, 0], 2));
+
+        let split = fee_split(2, &[3, 5]);
+        assert_eq!(split.shares(100), ([30, 50, 0, 0], 20));
+        assert_eq!(split.shares(99), ([29, 49, 0, 0], 21));
+    }
+
+    #[test]
+    fn fee_split_does_not_overflow() {
+        let max = u64::MAX;
+        let split = fee_split(u16::MAX, &[u16::MAX; MAX_FEE_BENEFICIARIES]);
+        assert_eq!(split.shares(max), ([max / 5; MAX_FEE_BENEFICIARIES], max / 5));
+
+        let split = fee_split(0, &[u16::MAX; MAX_FEE_BENEFICIARIES]);
+        assert_eq!(split.shares(max), ([max / 4; MAX_FEE_BENEFICIARIES], 3));
+
+        let (shares, treasury) = fee_split(u16::MAX, &[1, 1, 1, 1]).shares(max);
+        assert_eq!(shares.iter().sum::<u64>() + treasury, max);
+    }
+}